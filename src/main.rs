@@ -7,6 +7,24 @@
 
 mod maze {
     pub mod maze;
+    pub mod grid;
+    pub mod hex;
+    pub mod delta;
+    pub mod topology;
+    pub mod algorithms;
+    pub mod config;
+    pub mod braid;
+    pub mod solver;
+    pub mod metrics;
+    pub mod levels;
+    pub mod mask;
+    pub mod weave;
+    pub mod rooms;
+    pub mod format;
+    pub mod unicursal;
+    pub mod wrap;
+    pub mod surface;
+    pub mod puzzle;
     mod cell;
 }
 mod utils {
@@ -17,6 +35,8 @@ mod utils {
     pub mod walk;
     pub mod brick;
     pub mod rectangle;
+    pub mod svg;
+    pub mod stroke;
 }
 mod metadata {
     pub mod assets;
@@ -33,24 +53,76 @@ mod bevy {
 }
 
 use brickadia::save::Brick;
+use maze::config::MazeConfig;
+use maze::format::save_maze;
 use maze::maze::Maze;
-use utils::brick::save_bricks;
+use maze::puzzle::{place_puzzle, puzzle_bricks, puzzle_map, PuzzleConfig};
+use maze::topology::{Plate, Topology};
+use utils::brick::{save_bricks, CEILING_BRICK, FLOOR_BRICK, HINT_BRICK};
+use utils::pixels::{Bitmap, WHITE};
+use utils::walk::colour_bricks_at;
 
 const VOXEL_TESTING: bool = false;
+/// Turns this image into bricks of its colours instead of generating a maze
+const IMAGE_TO_BRICKS: Option<&str> = None;
+/// Builds the bricks straight from the arcs and walls of the maze instead of rasterizing them
+const ANALYTIC_BRICKS: bool = false;
 
 fn main() {
 
-    if VOXEL_TESTING {
+    if let Some(path) = IMAGE_TO_BRICKS {
+        let image = image::open(path).expect("Failed to open the image").to_rgb8();
+        // White is left empty, as the background of most pixel art and logos
+        let bricks = colour_bricks_at(&Bitmap::from_image(image), 1, 0, Some(WHITE));
+        save_bricks(bricks, "image");
+    }
+    else if VOXEL_TESTING {
         bevy::app::application();
     }
     else {
-        let mut maze = Maze { ring_gap: 10, rings: 60, initial_divisions: 4, solution: Vec::new() };
+        let mut maze = Maze { ring_gap: 10, rings: 60, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
         let seed = [11, 13, 15, 2];
         let wall_width = 5;
         let wall_height = 1;
         let granularity = 1.0;
         let solve = true;
-        let bricks: Vec<Brick> = maze.generate(seed, wall_width, wall_height, granularity, solve);
+        // A plate under the maze, and one over it to leave the maze in the dark
+        let floor = Some(Plate::Footprint);
+        let ceiling = false;
+        let plate_thickness = 1;
+        let cells = maze.get_cells(seed);
+        let solutions = maze.solutions(&cells);
+        let mut bricks: Vec<Brick> = if ANALYTIC_BRICKS {
+            maze.export_analytic(&cells, &solutions, wall_width, wall_height, solve)
+        } else {
+            maze.export(&cells, &solutions, wall_width, wall_height, granularity, solve)
+        };
+        if let Some(plate) = floor {
+            bricks.extend(maze.plate_bricks(plate, wall_width, plate_thickness, -2 * plate_thickness as i32, FLOOR_BRICK));
+        }
+        if ceiling {
+            bricks.extend(maze.plate_bricks(Plate::Footprint, wall_width, plate_thickness, 2 * wall_height as i32, CEILING_BRICK));
+        }
         save_bricks(bricks, "maze");
+        if let Err(error) = save_maze(&maze, &cells, "maze") {
+            println!("ERROR: {}", error);
+        }
+
+        // Locked doors along the solution, each with its key, and collectibles, saved as their own build
+        let puzzle = PuzzleConfig { keys: 3, collectibles: 5 };
+        if puzzle.keys > 0 || puzzle.collectibles > 0 {
+            let puzzle = place_puzzle(&maze, &cells, &puzzle, seed);
+            puzzle_map(&maze, &cells, &puzzle, wall_width).save("puzzle");
+            save_bricks(puzzle_bricks(&maze, &puzzle, wall_width, wall_height), "puzzle");
+        }
+
+        if solve {
+            // A plate on the floor between the walls. The walls reach up to `2 * wall_height`,
+            // so an elevation of at least that raises the hint above them.
+            let hint_thickness = 1;
+            let hint_elevation = 0;
+            let hint: Vec<Brick> = maze.hint_bricks(&[maze.solution().to_vec()], wall_width, hint_thickness, hint_elevation, HINT_BRICK);
+            save_bricks(hint, "hint");
+        }
     }
 }
@@ -0,0 +1,669 @@
+use std::collections::{HashMap, HashSet};
+
+use strum_macros::{Display, EnumIter};
+
+use crate::utils::sfc32::SFC32;
+use super::{cell::Cell, metrics::TURN_ANGLE, topology::Topology};
+
+/// Two adjacent cells, on either side of a wall
+pub type Edge = ((usize, usize), (usize, usize));
+
+/// How the next active cell is chosen by the growing tree algorithm
+#[derive(Debug, Clone, Copy, Default, EnumIter, Display, PartialEq)]
+pub enum Selection {
+    /// Behaves like the recursive backtracker
+    #[default]
+    Newest,
+    Oldest,
+    /// Behaves like Prim's algorithm
+    Random,
+    /// Picks the newest cell with the given probability, otherwise a random one
+    Mixed(f64),
+}
+
+/// Only the recursive backtracker and growing tree follow the directional biases of the config
+#[derive(Debug, Clone, Copy, Default, EnumIter, Display, PartialEq)]
+pub enum Algorithm {
+    /// Long winding corridors with few branches
+    #[default]
+    Backtracker,
+    /// Many short dead ends radiating from the start
+    Prim,
+    Kruskal,
+    /// A uniform spanning tree built from loop-erased random walks
+    Wilson,
+    /// A uniform spanning tree built from a single random walk
+    AldousBroder,
+    GrowingTree(Selection),
+    /// Splits the maze into two randomly grown regions joined by a single passage
+    RecursiveDivision,
+    /// Carves one row at a time, joining sets of cells horizontally and then downward
+    Eller,
+}
+
+/// Carves every unvisited cell into a spanning tree containing `start`.
+/// Cells which are already visited are left untouched. Where they split the unvisited cells into
+/// separate parts, such as a room across the whole maze, each part is carved into its own tree.
+/// Returns the cells in the order they were added to the maze.
+pub fn carve<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    start: (usize, usize),
+    algorithm: Algorithm,
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    // Cells visited before carving are not part of the maze
+    let region: Vec<Vec<bool>> = cells.iter().map(|row| row.iter().map(|cell| !cell.visited).collect()).collect();
+
+    let mut order = Vec::new();
+    for part in region_parts(topology, &region, start) {
+        let start = if part[start.0][start.1] { start } else { region_cells(&part)[0] };
+        order.extend(match algorithm {
+            Algorithm::Backtracker => growing_tree(topology, cells, start, Selection::Newest, s_random),
+            Algorithm::Prim => prim(topology, cells, &part, start, s_random),
+            Algorithm::Kruskal => kruskal(topology, cells, &part, s_random),
+            Algorithm::Wilson => wilson(topology, cells, &part, start, s_random),
+            Algorithm::AldousBroder => aldous_broder(topology, cells, &part, start, s_random),
+            Algorithm::GrowingTree(selection) => growing_tree(topology, cells, start, selection, s_random),
+            Algorithm::RecursiveDivision => recursive_division(topology, cells, &part, s_random),
+            Algorithm::Eller => eller(topology, cells, &part, s_random),
+        });
+    }
+    order
+}
+
+/// Splits a region into its connected parts, beginning with the part containing `start`
+fn region_parts<T: Topology + ?Sized>(topology: &T, region: &[Vec<bool>], start: (usize, usize)) -> Vec<Vec<Vec<bool>>> {
+
+    let mut remaining: Vec<Vec<bool>> = region.to_vec();
+    let mut parts = Vec::new();
+
+    let origins = std::iter::once(start).chain(region_cells(region));
+    for origin in origins {
+        if !remaining[origin.0][origin.1] {
+            continue;
+        }
+        let mut part: Vec<Vec<bool>> = region.iter().map(|row| vec![false; row.len()]).collect();
+        remaining[origin.0][origin.1] = false;
+        part[origin.0][origin.1] = true;
+        let mut stack = vec![origin];
+        while let Some(cell) = stack.pop() {
+            for (row, column) in region_neighbours(topology, region, cell) {
+                if remaining[row][column] {
+                    remaining[row][column] = false;
+                    part[row][column] = true;
+                    stack.push((row, column));
+                }
+            }
+        }
+        parts.push(part);
+    }
+
+    parts
+}
+
+/// Neighbours of a cell which belong to the carved region
+fn region_neighbours<T: Topology + ?Sized>(topology: &T, region: &[Vec<bool>], (row, column): (usize, usize)) -> Vec<(usize, usize)> {
+    topology.neighbours(row, column)
+        .into_iter()
+        .filter(|&(row, column)| region[row][column])
+        .collect()
+}
+
+fn region_cells(region: &[Vec<bool>]) -> Vec<(usize, usize)> {
+    region.iter().enumerate()
+        .flat_map(|(row, columns)| columns.iter().enumerate()
+            .filter(|&(_, &carved)| carved)
+            .map(move |(column, _)| (row, column)))
+        .collect()
+}
+
+fn visit(cells: &mut [Vec<Cell>], order: &mut Vec<(usize, usize)>, (row, column): (usize, usize)) {
+    cells[row][column].visited = true;
+    order.push((row, column));
+}
+
+/// A disjoint set forest over cells, used to track which cells are already connected
+pub struct Sets {
+    offsets: Vec<usize>,
+    parents: Vec<usize>,
+}
+impl Sets {
+    pub fn new(cells: &[Vec<Cell>]) -> Self {
+        let offsets: Vec<usize> = cells.iter()
+            .scan(0, |offset, row| { let start = *offset; *offset += row.len(); Some(start) })
+            .collect();
+        let count = cells.iter().map(Vec::len).sum();
+        Self { offsets, parents: (0..count).collect() }
+    }
+    pub fn find(&mut self, (row, column): (usize, usize)) -> usize {
+        let mut index = self.offsets[row] + column;
+        while self.parents[index] != index {
+            // Path halving keeps the trees shallow
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+    /// Joins the sets of two cells, returning false if they were already joined
+    pub fn union(&mut self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return false;
+        }
+        self.parents[root_b] = root_a;
+        true
+    }
+}
+
+/// Picks the neighbour to carve into, weighted by the radial bias and straightness of the config.
+/// `previous` is the cell the carver came from, which gives the direction it is heading in.
+fn choose<T: Topology + ?Sized>(
+    topology: &T,
+    cell: (usize, usize),
+    previous: Option<(usize, usize)>,
+    candidates: &[(usize, usize)],
+    s_random: &mut SFC32,
+) -> (usize, usize) {
+
+    let config = topology.config();
+    if config.radial_bias == 0.0 && config.straightness == 0.0 {
+        return candidates[s_random.rand_between(0, candidates.len())];
+    }
+
+    let centre = |(row, column): (usize, usize)| topology.cell_centre(row, column, 0);
+    let heading = |from: (usize, usize), to: (usize, usize)| {
+        let (a, b) = (centre(from), centre(to));
+        (b.1 - a.1).atan2(b.0 - a.0)
+    };
+
+    let weights: Vec<f64> = candidates.iter()
+        .map(|&next| {
+            let radial = if next.0 == cell.0 { 1.0 - config.radial_bias } else { 1.0 + config.radial_bias };
+            let straight = previous.map_or(1.0, |previous| {
+                let change = (heading(cell, next) - heading(previous, cell)).rem_euclid(std::f64::consts::TAU);
+                if change.min(std::f64::consts::TAU - change) > TURN_ANGLE { 1.0 - config.straightness } else { 1.0 + config.straightness }
+            });
+            (radial * straight).max(0.0)
+        })
+        .collect();
+
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return candidates[s_random.rand_between(0, candidates.len())];
+    }
+
+    let mut target = s_random.rand_f64() * total;
+    for (&next, weight) in candidates.iter().zip(weights) {
+        if target < weight {
+            return next;
+        }
+        target -= weight;
+    }
+    candidates[candidates.len() - 1]
+}
+
+fn growing_tree<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    start: (usize, usize),
+    selection: Selection,
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    visit(cells, &mut order, start);
+
+    let mut active = vec![start];
+    let mut parents: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+
+    while !active.is_empty() {
+
+        let index = match selection {
+            Selection::Newest => active.len() - 1,
+            Selection::Oldest => 0,
+            Selection::Random => s_random.rand_between(0, active.len()),
+            Selection::Mixed(newest) => if s_random.rand_f64() < newest {
+                active.len() - 1
+            } else {
+                s_random.rand_between(0, active.len())
+            },
+        };
+        let (row, column) = active[index];
+
+        let unvisited = topology.unvisited_neighbours(cells, row, column);
+
+        if unvisited.is_empty() {
+            // Retire cells with no unvisited neighbours
+            active.remove(index);
+        } else {
+            let next = choose(topology, (row, column), parents.get(&(row, column)).copied(), &unvisited, s_random);
+            topology.open_wall_between(cells, (row, column), next);
+            visit(cells, &mut order, next);
+            parents.insert(next, (row, column));
+            active.push(next);
+        }
+    }
+
+    order
+}
+
+fn prim<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    start: (usize, usize),
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    visit(cells, &mut order, start);
+
+    let mut frontier: Vec<(usize, usize)> = topology.unvisited_neighbours(cells, start.0, start.1);
+    let mut in_frontier: HashSet<(usize, usize)> = frontier.iter().copied().collect();
+
+    while !frontier.is_empty() {
+
+        let cell = frontier.swap_remove(s_random.rand_between(0, frontier.len()));
+
+        // Join the frontier cell to a random neighbour already in the maze
+        let carved: Vec<(usize, usize)> = region_neighbours(topology, region, cell)
+            .into_iter()
+            .filter(|&(row, column)| cells[row][column].visited)
+            .collect();
+        let neighbour = carved[s_random.rand_between(0, carved.len())];
+        topology.open_wall_between(cells, cell, neighbour);
+        visit(cells, &mut order, cell);
+
+        for next in topology.unvisited_neighbours(cells, cell.0, cell.1) {
+            if in_frontier.insert(next) {
+                frontier.push(next);
+            }
+        }
+    }
+
+    order
+}
+
+fn kruskal<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    let mut sets = Sets::new(cells);
+
+    let mut edges: Vec<Edge> = region_cells(region)
+        .into_iter()
+        .flat_map(|cell| region_neighbours(topology, region, cell)
+            .into_iter()
+            .filter(move |&neighbour| cell < neighbour)
+            .map(move |neighbour| (cell, neighbour)))
+        .collect();
+
+    // Passages opened before carving, such as the crossings of a weave, are kept
+    for &(a, b) in &edges {
+        if topology.linked(cells, a, b) {
+            sets.union(a, b);
+        }
+    }
+
+    s_random.shuffle(&mut edges);
+
+    for (a, b) in edges {
+        if sets.union(a, b) {
+            topology.open_wall_between(cells, a, b);
+            for cell in [a, b] {
+                if !cells[cell.0][cell.1].visited {
+                    visit(cells, &mut order, cell);
+                }
+            }
+        }
+    }
+
+    // Cells joined only by passages opened before carving
+    for cell in region_cells(region) {
+        if !cells[cell.0][cell.1].visited {
+            visit(cells, &mut order, cell);
+        }
+    }
+
+    order
+}
+
+fn wilson<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    start: (usize, usize),
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    visit(cells, &mut order, start);
+
+    let mut remaining = region_cells(region);
+    s_random.shuffle(&mut remaining);
+
+    for origin in remaining {
+        if cells[origin.0][origin.1].visited {
+            continue;
+        }
+
+        // Random walk until the maze is reached, remembering only the last exit from each cell.
+        // Overwriting exits erases any loops in the walk.
+        let mut exits: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+        let mut cell = origin;
+        while !cells[cell.0][cell.1].visited {
+            let neighbours = region_neighbours(topology, region, cell);
+            if neighbours.is_empty() {
+                break;
+            }
+            let next = neighbours[s_random.rand_between(0, neighbours.len())];
+            exits.insert(cell, next);
+            cell = next;
+        }
+
+        // Carve the loop-erased walk into the maze
+        let mut cell = origin;
+        while !cells[cell.0][cell.1].visited {
+            let Some(&next) = exits.get(&cell) else { break };
+            topology.open_wall_between(cells, cell, next);
+            visit(cells, &mut order, cell);
+            cell = next;
+        }
+    }
+
+    order
+}
+
+fn aldous_broder<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    start: (usize, usize),
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    visit(cells, &mut order, start);
+
+    let mut remaining = region_cells(region).len() - 1;
+    let mut cell = start;
+
+    while remaining > 0 {
+        let neighbours = region_neighbours(topology, region, cell);
+        if neighbours.is_empty() {
+            break;
+        }
+        let next = neighbours[s_random.rand_between(0, neighbours.len())];
+        if !cells[next.0][next.1].visited {
+            topology.open_wall_between(cells, cell, next);
+            visit(cells, &mut order, next);
+            remaining -= 1;
+        }
+        cell = next;
+    }
+
+    order
+}
+
+/// Recursive division generalised to any topology: a region is split in two by
+/// growing two random seeds, and the walls between the halves are closed but for one passage.
+fn recursive_division<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut closed: HashSet<Edge> = HashSet::new();
+
+    // An explicit stack avoids deep recursion in large mazes
+    let mut regions = vec![region_cells(region)];
+
+    while let Some(cells_in_region) = regions.pop() {
+
+        if cells_in_region.len() < 2 {
+            continue;
+        }
+
+        let mut sides: HashMap<(usize, usize), bool> = cells_in_region.iter().map(|&cell| (cell, false)).collect();
+        let mut assigned: HashSet<(usize, usize)> = HashSet::new();
+
+        // Two distinct seeds, one for each half
+        let a = s_random.rand_between(0, cells_in_region.len());
+        let b = (a + s_random.rand_between(1, cells_in_region.len())) % cells_in_region.len();
+        let mut frontier = vec![cells_in_region[a], cells_in_region[b]];
+        sides.insert(cells_in_region[b], true);
+        assigned.extend(frontier.iter().copied());
+
+        while !frontier.is_empty() {
+            let index = s_random.rand_between(0, frontier.len());
+            let cell = frontier[index];
+            let side = sides[&cell];
+
+            let unassigned: Vec<(usize, usize)> = topology.neighbours(cell.0, cell.1)
+                .into_iter()
+                .filter(|neighbour| sides.contains_key(neighbour) && !assigned.contains(neighbour))
+                .collect();
+
+            if unassigned.is_empty() {
+                frontier.swap_remove(index);
+            } else {
+                let next = unassigned[s_random.rand_between(0, unassigned.len())];
+                sides.insert(next, side);
+                assigned.insert(next);
+                frontier.push(next);
+            }
+        }
+
+        // Close every wall between the two halves except one
+        let mut boundary: Vec<Edge> = cells_in_region.iter()
+            .filter(|cell| !sides[cell])
+            .flat_map(|&cell| topology.neighbours(cell.0, cell.1)
+                .into_iter()
+                .filter(|neighbour| sides.get(neighbour) == Some(&true))
+                .map(move |neighbour| (cell.min(neighbour), cell.max(neighbour))))
+            .collect();
+        boundary.sort();
+        boundary.dedup();
+
+        if !boundary.is_empty() {
+            boundary.swap_remove(s_random.rand_between(0, boundary.len()));
+        }
+        closed.extend(boundary);
+
+        let (first, second): (Vec<_>, Vec<_>) = cells_in_region
+            .into_iter()
+            .partition(|cell| !sides[cell]);
+        regions.push(first);
+        regions.push(second);
+    }
+
+    // Every wall inside the region which was not closed is a passage
+    let mut order = Vec::new();
+    for cell in region_cells(region) {
+        for neighbour in region_neighbours(topology, region, cell) {
+            if cell < neighbour && !closed.contains(&(cell, neighbour)) {
+                topology.open_wall_between(cells, cell, neighbour);
+            }
+        }
+        visit(cells, &mut order, cell);
+    }
+
+    order
+}
+
+fn eller<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    region: &[Vec<bool>],
+    s_random: &mut SFC32,
+) -> Vec<(usize, usize)> {
+
+    let mut order = Vec::new();
+    let mut sets = Sets::new(cells);
+    let last_row = topology.rows() - 1;
+
+    for row in 0..topology.rows() {
+
+        let row_cells: Vec<(usize, usize)> = (0..topology.columns(row))
+            .filter(|&column| region[row][column])
+            .map(|column| (row, column))
+            .collect();
+
+        // Each pair of cells is taken once, including the pair across a seam where the row wraps around
+        let same_row = |cell: (usize, usize)| -> Vec<(usize, usize)> {
+            region_neighbours(topology, region, cell)
+                .into_iter()
+                .filter(|&neighbour| neighbour.0 == row && cell < neighbour)
+                .collect()
+        };
+
+        // Randomly join adjacent cells of different sets, joining all of them in the last row
+        for &cell in &row_cells {
+            for neighbour in same_row(cell) {
+                if (row == last_row || s_random.rand_f64() < 0.5) && sets.union(cell, neighbour) {
+                    topology.open_wall_between(cells, cell, neighbour);
+                }
+            }
+        }
+
+        if row == last_row {
+            break;
+        }
+
+        // Group the cells of this row by set, with the cells each can carve downward into.
+        // A seam may lead further down, such as the twisted seam of a Mobius strip.
+        let mut groups: HashMap<usize, Vec<Edge>> = HashMap::new();
+        let mut roots = Vec::new();
+        for &cell in &row_cells {
+            let root = sets.find(cell);
+            let group = groups.entry(root).or_insert_with(|| { roots.push(root); Vec::new() });
+            for below in region_neighbours(topology, region, cell) {
+                if below.0 > row {
+                    group.push((cell, below));
+                }
+            }
+        }
+
+        for root in roots {
+            let mut downward = groups.remove(&root).unwrap_or_default();
+
+            if downward.is_empty() {
+                // A set with no way down must join a neighbouring set to stay connected
+                let members: Vec<(usize, usize)> = row_cells.iter().copied().filter(|&cell| sets.find(cell) == root).collect();
+                'join: for cell in members {
+                    for neighbour in region_neighbours(topology, region, cell) {
+                        if neighbour.0 == row && sets.union(cell, neighbour) {
+                            topology.open_wall_between(cells, cell, neighbour);
+                            break 'join;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Every set carves downward at least once
+            s_random.shuffle(&mut downward);
+            for (index, (cell, below)) in downward.into_iter().enumerate() {
+                if (index == 0 || s_random.rand_f64() < 0.5) && sets.union(cell, below) {
+                    topology.open_wall_between(cells, cell, below);
+                }
+            }
+        }
+    }
+
+    // Sets cut off by the shape of the region, such as the end of a masked arm, join any set they touch
+    for cell in region_cells(region) {
+        for neighbour in region_neighbours(topology, region, cell) {
+            if sets.union(cell, neighbour) {
+                topology.open_wall_between(cells, cell, neighbour);
+            }
+        }
+    }
+
+    for cell in region_cells(region) {
+        visit(cells, &mut order, cell);
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::maze::{config::MazeConfig, delta::DeltaGrid, grid::Grid, hex::HexGrid, maze::Maze, topology::Topology};
+
+    use super::Algorithm;
+
+    /// A perfect maze is a spanning tree: every cell is reachable and there is exactly one passage fewer than cells
+    fn assert_perfect(topology: &mut dyn Topology, algorithm: Algorithm) {
+        let cells = topology.get_cells([11, 13, 15, 2]);
+
+        let mut count = 0;
+        let mut passages = 0;
+        for row in 0..topology.rows() {
+            for column in 0..topology.columns(row) {
+                assert!(cells[row][column].visited, "{algorithm}: ({row}, {column}) was never visited");
+                count += 1;
+                passages += topology.neighbours(row, column)
+                    .into_iter()
+                    .filter(|&neighbour| (row, column) < neighbour && topology.linked(&cells, (row, column), neighbour))
+                    .count();
+            }
+        }
+        assert_eq!(passages, count - 1, "{algorithm}: the maze is not a spanning tree");
+        assert!(!topology.solution().is_empty(), "{algorithm}: there is no solution");
+    }
+
+    #[test]
+    fn algorithms_carve_perfect_mazes() {
+        for algorithm in Algorithm::iter() {
+            let config = MazeConfig { algorithm, ..Default::default() };
+            assert_perfect(&mut Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: config.clone() }, algorithm);
+            assert_perfect(&mut HexGrid { cell_size: 10, width: 9, height: 7, solution: Vec::new(), config: config.clone() }, algorithm);
+            assert_perfect(&mut DeltaGrid { cell_size: 10, width: 11, height: 6, solution: Vec::new(), config: config.clone() }, algorithm);
+        }
+    }
+
+    #[test]
+    fn straightness_lengthens_corridors() {
+        let river_factor = |straightness: f64| {
+            let config = MazeConfig { straightness, ..Default::default() };
+            let mut grid = Grid { cell_size: 10, width: 20, height: 20, solution: Vec::new(), config };
+            let cells = grid.get_cells([11, 13, 15, 2]);
+            grid.metrics(&cells).river_factor
+        };
+        assert!(river_factor(0.9) > river_factor(-0.9));
+    }
+
+    #[test]
+    fn radial_bias_favours_passages_between_rings() {
+        let radial_share = |radial_bias: f64| {
+            let config = MazeConfig { radial_bias, ..Default::default() };
+            let mut maze = Maze { ring_gap: 10, rings: 16, initial_divisions: 4, solution: Vec::new(), config };
+            let cells = maze.get_cells([11, 13, 15, 2]);
+            let metrics = maze.metrics(&cells);
+            metrics.radial_passages as f64 / metrics.passages as f64
+        };
+        let (unbiased, biased) = (radial_share(0.0), radial_share(0.8));
+        assert!(biased > unbiased + 0.05, "{biased} is not clearly above {unbiased}");
+    }
+
+    #[test]
+    fn algorithms_reach_the_centre() {
+        for algorithm in Algorithm::iter() {
+            let config = MazeConfig { algorithm, ..Default::default() };
+            let mut maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config };
+            maze.get_cells([11, 13, 15, 2]);
+            assert_eq!(maze.solution.first(), Some(&(7, 0)), "{algorithm}");
+            assert_eq!(maze.solution.last().map(|&(ring, _)| ring), Some(0), "{algorithm}");
+        }
+    }
+}
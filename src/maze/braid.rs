@@ -0,0 +1,110 @@
+use crate::utils::sfc32::SFC32;
+use super::{cell::Cell, topology::Topology};
+
+/// The number of passages leading out of a cell
+pub fn degree<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], cell: (usize, usize)) -> usize {
+    topology.neighbours(cell.0, cell.1)
+        .into_iter()
+        .filter(|&neighbour| topology.linked(cells, cell, neighbour))
+        .count()
+}
+
+fn is_dead_end<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], cell: (usize, usize)) -> bool {
+    degree(topology, cells, cell) == 1
+}
+
+/// Removes a `fraction` of the dead ends by opening one of their closed walls.
+/// Walls towards other dead ends are preferred, so that one opening removes two dead ends.
+pub fn remove_dead_ends<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    fraction: f64,
+    s_random: &mut SFC32,
+) {
+    if fraction <= 0.0 {
+        return;
+    }
+
+    let mut dead_ends: Vec<(usize, usize)> = (0..topology.rows())
+        .flat_map(|row| (0..topology.columns(row)).map(move |column| (row, column)))
+        .filter(|&cell| cells[cell.0][cell.1].visited && is_dead_end(topology, cells, cell))
+        .collect();
+
+    s_random.shuffle(&mut dead_ends);
+
+    for cell in dead_ends {
+        // An earlier opening may already have removed this dead end
+        if !is_dead_end(topology, cells, cell) || s_random.rand_f64() >= fraction {
+            continue;
+        }
+
+        let closed: Vec<(usize, usize)> = topology.neighbours(cell.0, cell.1)
+            .into_iter()
+            .filter(|&neighbour| cells[neighbour.0][neighbour.1].visited && !topology.linked(cells, cell, neighbour))
+            .collect();
+
+        let dead_end_neighbours: Vec<(usize, usize)> = closed.iter()
+            .copied()
+            .filter(|&neighbour| is_dead_end(topology, cells, neighbour))
+            .collect();
+
+        let candidates = if dead_end_neighbours.is_empty() { closed } else { dead_end_neighbours };
+
+        if !candidates.is_empty() {
+            let neighbour = candidates[s_random.rand_between(0, candidates.len())];
+            topology.open_wall_between(cells, cell, neighbour);
+        }
+    }
+}
+
+/// Opens `count` random walls between adjacent cells, each of which creates a loop in the maze
+pub fn add_loops<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    count: usize,
+    s_random: &mut SFC32,
+) {
+    if count == 0 {
+        return;
+    }
+
+    let mut walls: Vec<((usize, usize), (usize, usize))> = (0..topology.rows())
+        .flat_map(|row| (0..topology.columns(row)).map(move |column| (row, column)))
+        .filter(|&(row, column)| cells[row][column].visited)
+        .flat_map(|cell| topology.neighbours(cell.0, cell.1)
+            .into_iter()
+            .filter(move |&neighbour| cell < neighbour)
+            .map(move |neighbour| (cell, neighbour)))
+        .filter(|&(cell, neighbour)| cells[neighbour.0][neighbour.1].visited && !topology.linked(cells, cell, neighbour))
+        .collect();
+
+    walls.sort();
+    walls.dedup();
+    s_random.shuffle(&mut walls);
+
+    for (cell, neighbour) in walls.into_iter().take(count) {
+        topology.open_wall_between(cells, cell, neighbour);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::MazeConfig, grid::Grid, topology::Topology};
+
+    use super::degree;
+
+    #[test]
+    fn braid_removes_every_dead_end() {
+        let config = MazeConfig { dead_end_removal: 1.0, ..Default::default() };
+        let mut grid = Grid { cell_size: 10, width: 15, height: 10, solution: Vec::new(), config };
+        let cells = grid.get_cells([11, 13, 15, 2]);
+
+        for row in 0..grid.rows() {
+            for column in 0..grid.columns(row) {
+                assert!(degree(&grid, &cells, (row, column)) > 1, "({row}, {column}) is a dead end");
+            }
+        }
+        assert_eq!(grid.solution.first(), Some(&grid.entrance()));
+        assert_eq!(grid.solution.last(), Some(&grid.exit()));
+    }
+}
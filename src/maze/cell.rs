@@ -2,17 +2,46 @@
 pub struct Cell {
     pub visited: bool,
     pub inner_wall: bool,
+    /// The second inner wall of cells with two inner neighbours: hexagons, and cells of a circular maze
+    /// reaching over two cells of the ring inside
+    pub inner_right_wall: bool,
     pub outer_wall: bool,
     pub right_wall: bool,
 }
 
+/// The walls owned by a `Cell`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wall {
+    Inner,
+    InnerRight,
+    Outer,
+    Right,
+}
+
 impl Cell {
     pub fn new() -> Self {
         Self {
             visited: false,
             inner_wall: true,
+            inner_right_wall: true,
             outer_wall: true,
             right_wall: true,
         }
     }
-}
\ No newline at end of file
+    pub fn wall(&self, wall: Wall) -> bool {
+        match wall {
+            Wall::Inner => self.inner_wall,
+            Wall::InnerRight => self.inner_right_wall,
+            Wall::Outer => self.outer_wall,
+            Wall::Right => self.right_wall,
+        }
+    }
+    pub fn wall_mut(&mut self, wall: Wall) -> &mut bool {
+        match wall {
+            Wall::Inner => &mut self.inner_wall,
+            Wall::InnerRight => &mut self.inner_right_wall,
+            Wall::Outer => &mut self.outer_wall,
+            Wall::Right => &mut self.right_wall,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use strum_macros::{Display, EnumIter};
+
+use super::{algorithms::Algorithm, rooms::Room, solver::Search};
+
+/// Where the solution of a maze ends
+#[derive(Debug, Clone, Copy, Default, EnumIter, Display, PartialEq)]
+pub enum Goal {
+    /// The exit on the boundary, or the centre of a circular maze
+    #[default]
+    Exit,
+    /// The cell closest to the middle of the maze
+    Centre,
+    /// Any cell, chosen with the seed
+    Random,
+    /// The cell whose distance to the nearest entrance is greatest
+    Farthest,
+}
+
+/// Options controlling how the passages of a maze are carved, shared by every topology
+#[derive(Clone, Default)]
+pub struct MazeConfig {
+    pub algorithm: Algorithm,
+    /// The fraction of dead ends removed after carving, from 0 (a perfect maze) to 1 (a braid maze)
+    pub dead_end_removal: f64,
+    /// The number of additional walls opened at random, each of which adds a loop
+    pub loops: usize,
+    /// The search used to find the solution
+    pub search: Search,
+    /// The number of entrances spread evenly along the outer boundary, at least one
+    pub entrances: usize,
+    pub goal: Goal,
+    /// Open chambers placed before carving
+    pub rooms: Vec<Room>,
+    /// From -1 to 1, favours carving between rows, the rings of a circular maze, when positive
+    /// and along them when negative, for spoke-like or spiral-like mazes
+    pub radial_bias: f64,
+    /// From -1 to 1, favours carving straight on when positive, for long corridors,
+    /// and turning when negative, for twisty ones
+    pub straightness: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::maze::{grid::Grid, mask::MaskGrid, maze::Maze, topology::Topology};
+    use crate::utils::sfc32::SFC32;
+
+    use super::{Goal, MazeConfig};
+
+    #[test]
+    fn goals_are_reached_from_every_entrance() {
+        for goal in Goal::iter() {
+            let config = MazeConfig { entrances: 3, goal, ..Default::default() };
+            let mut grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: config.clone() };
+            let mut maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config };
+            let topologies: [&mut dyn Topology; 2] = [&mut grid, &mut maze];
+
+            for topology in topologies {
+                let cells = topology.get_cells([11, 13, 15, 2]);
+                let entrances = topology.entrances();
+                assert_eq!(entrances.len(), 3, "{goal}");
+                for &(row, column) in &entrances {
+                    assert!(!cells[row][column].outer_wall, "{goal}: ({row}, {column}) was not opened");
+                }
+
+                let solutions = topology.solutions(&cells);
+                assert_eq!(solutions.len(), 3, "{goal}");
+                for (solution, entrance) in solutions.iter().zip(&entrances) {
+                    assert_eq!(solution.first(), Some(entrance), "{goal}");
+                    assert_eq!(solution.last(), topology.solution().last(), "{goal}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_goal_of_an_empty_maze_is_the_start() {
+        let config = MazeConfig { goal: Goal::Random, ..Default::default() };
+        let grid = MaskGrid { cell_size: 10, mask: vec![vec![false; 4]; 3], solution: Vec::new(), config };
+        let cells = grid.new_cells();
+        assert_eq!(grid.goal(&cells, &[(2, 0)], &mut SFC32::new([11, 13, 15, 2])), (2, 0));
+    }
+}
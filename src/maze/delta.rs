@@ -0,0 +1,174 @@
+use image::Rgb;
+
+use crate::utils::pixels::Bitmap;
+use super::{cell::{Cell, Wall}, config::MazeConfig, topology::{extended, Topology, Walls}};
+
+/// A `width` x `height` maze of alternating upward and downward pointing triangles.
+/// The cell at `(row, column)` points upward when `row + column` is even.
+/// Downward triangles own their top edge as `inner_wall`, every cell owns its
+/// right edge as `right_wall` and `outer_wall` is the base of upward triangles in the last row.
+/// A single column of triangles only holds together over its first two rows, so `width` should be at least 2.
+pub struct DeltaGrid {
+    /// The length of a side of a triangle in pixels
+    pub cell_size: u32,
+    pub width: usize,
+    pub height: usize,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl DeltaGrid {
+
+    fn points_up(row: usize, column: usize) -> bool {
+        (row + column).is_multiple_of(2)
+    }
+
+    fn cell_height(&self) -> f64 {
+        self.cell_size as f64 * 3f64.sqrt() / 2.0
+    }
+
+    /// The corners `(left, right, apex)` of a triangle
+    fn vertices(&self, row: usize, column: usize, wall_width: u32) -> [(f64, f64); 3] {
+        let size = self.cell_size as f64;
+        let margin = wall_width as f64;
+        let left = margin + column as f64 * size / 2.0;
+        let top = margin + row as f64 * self.cell_height();
+        let bottom = top + self.cell_height();
+
+        if Self::points_up(row, column) {
+            [(left, bottom), (left + size, bottom), (left + size / 2.0, top)]
+        } else {
+            [(left, top), (left + size, top), (left + size / 2.0, bottom)]
+        }
+    }
+}
+
+impl Topology for DeltaGrid {
+
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(3);
+
+        if column + 1 < self.width {
+            neighbours.push((row, column + 1));
+        }
+        if column > 0 {
+            neighbours.push((row, column - 1));
+        }
+        if Self::points_up(row, column) {
+            if row + 1 < self.height {
+                neighbours.push((row + 1, column));
+            }
+        } else if row > 0 {
+            neighbours.push((row - 1, column));
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if row_a == row_b {
+            // Same row: the leftmost cell owns its right wall
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else {
+            // Different rows: the lower, downward pointing cell owns its top edge
+            ((row_a.max(row_b), column_a), Wall::Inner)
+        }
+    }
+
+    /// The entrance is the first upward triangle of the last row, whose base lies on the boundary,
+    /// or the only cell of the last row in a grid one column wide
+    fn entrance(&self) -> (usize, usize) {
+        let row = self.height - 1;
+        (row, (row % 2).min(self.width - 1))
+    }
+
+    /// The upward triangles of the last row, whose bases lie on the boundary
+    fn boundary(&self) -> Vec<(usize, usize)> {
+        let row = self.height - 1;
+        (row % 2..self.width).step_by(2).map(|column| (row, column)).collect()
+    }
+
+    /// The exit is the last downward triangle of the first row, whose top edge lies on the boundary.
+    /// A grid one column wide has none, so its only cell in the first row is the exit instead.
+    fn exit(&self) -> (usize, usize) {
+        let column = self.width - 1;
+        (0, if Self::points_up(0, column) { column.saturating_sub(1) } else { column })
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let last_row = self.height - 1;
+
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            for (column, cell) in row_cells.iter().enumerate() {
+                let [left, right, apex] = self.vertices(row, column, wall_width);
+
+                let walls = if Self::points_up(row, column) {
+                    [
+                        (cell.right_wall, apex, right),
+                        (column == 0, left, apex),
+                        (row == last_row && cell.outer_wall, left, right),
+                    ]
+                } else {
+                    [
+                        (cell.right_wall, right, apex),
+                        (column == 0, left, apex),
+                        (cell.inner_wall, left, right),
+                    ]
+                };
+
+                for (standing, begin, end) in walls {
+                    if standing {
+                        lines.push(extended(begin, end, mid_wall));
+                    }
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        // The centroid of the three corners
+        let [left, right, apex] = self.vertices(row, column, wall_width);
+        ((left.0 + right.0 + apex.0) / 3.0, (left.1 + right.1 + apex.1) / 3.0)
+    }
+
+    fn fill_cell(&self, bitmap: &mut Bitmap, row: usize, column: usize, wall_width: u32, rgb: Rgb<u8>) {
+        bitmap.polygon(&self.vertices(row, column, wall_width), rgb);
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let margin = 2.0 * wall_width as f64;
+        let width = self.cell_size as f64 * (self.width as f64 + 1.0) / 2.0 + margin;
+        let height = self.cell_height() * self.height as f64 + margin;
+        (width.ceil() as u32, height.ceil() as u32)
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt::Display;
+use std::fs;
+
+use strum::IntoEnumIterator;
+
+use super::{cell::Cell, config::MazeConfig, maze::Maze, rooms::Room};
+
+/// The first line of every saved maze, followed by the version of the format
+const HEADER: &str = "circular-maze";
+const VERSION: u32 = 1;
+
+/// The bit of each wall in the hexadecimal digit written for a cell
+const INNER: u8 = 1;
+const INNER_RIGHT: u8 = 2;
+const OUTER: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// Writes the parameters of a circular maze, the parts of its config used to solve it, the walls of its cells
+/// and its solution as text:
+///
+/// ```text
+/// circular-maze 1
+/// ring_gap 10
+/// rings 3
+/// initial_divisions 4
+/// entrances 1
+/// goal Exit
+/// search BreadthFirst
+/// rooms
+/// solution 2,0 2,1 2,2 2,3 2,4 1,2 1,1 1,0 1,8 2,14 1,7 2,13 2,12 1,6 2,10 1,5 1,4 1,3 0,1
+/// cells
+/// 7777
+/// 77f67fff7
+/// 3777e5777f4f6e4f
+/// ```
+///
+/// Each cell is one hexadecimal digit, one line per ring, whose bits are set for
+/// the standing inner (1), second inner (2), outer (4) and right (8) walls. Each room is written as
+/// `row,column,rows,columns,doors`. The carving options are left out, since the cells are already carved.
+pub fn write_maze(maze: &Maze, cells: &[Vec<Cell>]) -> String {
+
+    let mut text = format!("{} {}\n", HEADER, VERSION);
+    text += &format!("ring_gap {}\n", maze.ring_gap);
+    text += &format!("rings {}\n", maze.rings);
+    text += &format!("initial_divisions {}\n", maze.initial_divisions);
+    text += &format!("entrances {}\n", maze.config.entrances);
+    text += &format!("goal {}\n", maze.config.goal);
+    text += &format!("search {}\n", maze.config.search);
+
+    let rooms: Vec<String> = maze.config.rooms.iter()
+        .map(|room| format!("{},{},{},{},{}", room.row, room.column, room.rows, room.columns, room.doors))
+        .collect();
+    text += &format!("rooms {}\n", rooms.join(" "));
+
+    let solution: Vec<String> = maze.solution.iter().map(|(ring, division)| format!("{},{}", ring, division)).collect();
+    text += &format!("solution {}\n", solution.join(" "));
+
+    text += "cells\n";
+    for ring in cells {
+        let digits: String = ring.iter()
+            .map(|cell| {
+                let flags = [(cell.inner_wall, INNER), (cell.inner_right_wall, INNER_RIGHT), (cell.outer_wall, OUTER), (cell.right_wall, RIGHT)]
+                    .into_iter()
+                    .filter(|&(standing, _)| standing)
+                    .fold(0, |flags, (_, bit)| flags | bit);
+                char::from_digit(flags as u32, 16).unwrap()
+            })
+            .collect();
+        text += &digits;
+        text += "\n";
+    }
+
+    text
+}
+
+/// Reads a maze written by `write_maze`, with every cell marked as visited and the carving options left to their defaults
+pub fn parse_maze(text: &str) -> Result<(Maze, Vec<Vec<Cell>>), String> {
+
+    let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+
+    let mut next_line = |expected: &str| lines.next().ok_or(format!("Unexpected end of file, expected {}", expected));
+
+    let (number, line) = next_line(HEADER)?;
+    match line.split_once(' ') {
+        Some((HEADER, version)) if version.parse() == Ok(VERSION) => {}
+        _ => return Err(format!("Line {}: expected `{} {}`", number, HEADER, VERSION)),
+    }
+
+    let mut field = |name: &str| -> Result<(usize, String), String> {
+        let (number, line) = next_line(name)?;
+        match line.split_once(' ') {
+            Some((key, value)) if key == name => Ok((number, value.to_string())),
+            _ if line == name => Ok((number, String::new())),
+            _ => Err(format!("Line {}: expected `{}`", number, name)),
+        }
+    };
+
+    let parse_number = |name: &str, (line, value): (usize, String)| -> Result<usize, String> {
+        value.parse().map_err(|_| format!("Line {}: `{}` is not a valid {}", line, value, name))
+    };
+
+    let (ring_gap_line, ring_gap_text) = field("ring_gap")?;
+    let ring_gap = ring_gap_text.parse::<u32>().map_err(|_| format!("Line {}: `{}` is not a valid ring_gap", ring_gap_line, ring_gap_text))?;
+    let rings = parse_number("rings", field("rings")?)?;
+    let initial_divisions = parse_number("initial_divisions", field("initial_divisions")?)?;
+
+    let entrances = parse_number("entrances", field("entrances")?)?;
+    let goal = parse_variant("goal", field("goal")?)?;
+    let search = parse_variant("search", field("search")?)?;
+
+    let (rooms_line, rooms_text) = field("rooms")?;
+    let rooms = rooms_text.split_whitespace()
+        .map(|room| {
+            let numbers = room.split(',').map(|number| number.parse().ok()).collect::<Option<Vec<usize>>>();
+            match numbers.as_deref() {
+                Some(&[row, column, rows, columns, doors]) => Ok(Room { row, column, rows, columns, doors }),
+                _ => Err(format!("Line {}: `{}` is not a room", rooms_line, room)),
+            }
+        })
+        .collect::<Result<Vec<Room>, String>>()?;
+
+    let config = MazeConfig { entrances, goal, search, rooms, ..Default::default() };
+
+    let (solution_line, solution_text) = field("solution")?;
+    let solution = solution_text.split_whitespace()
+        .map(|cell| cell.split_once(',')
+            .and_then(|(ring, division)| Some((ring.parse().ok()?, division.parse().ok()?)))
+            .ok_or(format!("Line {}: `{}` is not a cell", solution_line, cell)))
+        .collect::<Result<Vec<(usize, usize)>, String>>()?;
+
+    field("cells")?;
+
+    let maze = Maze { ring_gap, rings, initial_divisions, solution, config };
+
+    let cells = (0..rings)
+        .map(|ring| {
+            let (number, line) = next_line("a ring of cells")?;
+            if line.chars().count() != maze.divisions_in_ring(ring) {
+                return Err(format!("Line {}: ring {} has {} cells instead of {}", number, ring, line.chars().count(), maze.divisions_in_ring(ring)));
+            }
+            line.chars()
+                .map(|digit| {
+                    let flags = digit.to_digit(16).ok_or(format!("Line {}: `{}` is not a hexadecimal digit", number, digit))? as u8;
+                    Ok(Cell {
+                        visited: true,
+                        inner_wall: flags & INNER != 0,
+                        inner_right_wall: flags & INNER_RIGHT != 0,
+                        outer_wall: flags & OUTER != 0,
+                        right_wall: flags & RIGHT != 0,
+                    })
+                })
+                .collect::<Result<Vec<Cell>, String>>()
+        })
+        .collect::<Result<Vec<Vec<Cell>>, String>>()?;
+
+    if let Some(&(ring, division)) = maze.solution.iter().find(|&&(ring, division)| ring >= rings || division >= maze.divisions_in_ring(ring)) {
+        return Err(format!("Line {}: ({}, {}) is not a cell of the maze", solution_line, ring, division));
+    }
+
+    Ok((maze, cells))
+}
+
+/// The variant of an enum whose name is `value`, as written by its `Display`
+fn parse_variant<T: IntoEnumIterator + Display>(name: &str, (line, value): (usize, String)) -> Result<T, String> {
+    T::iter()
+        .find(|variant| variant.to_string() == value)
+        .ok_or(format!("Line {}: `{}` is not a valid {}", line, value, name))
+}
+
+/// Writes a maze to `{name}.maze`
+pub fn save_maze(maze: &Maze, cells: &[Vec<Cell>], name: &str) -> Result<(), String> {
+    let path = format!("{}.maze", name);
+    fs::write(&path, write_maze(maze, cells)).map_err(|error| format!("Failed to save {}: {}", path, error))
+}
+
+/// Reads a maze from `{name}.maze`
+pub fn load_maze(name: &str) -> Result<(Maze, Vec<Vec<Cell>>), String> {
+    let path = format!("{}.maze", name);
+    let text = fs::read_to_string(&path).map_err(|error| format!("Failed to read {}: {}", path, error))?;
+    parse_maze(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::{Goal, MazeConfig}, maze::Maze, rooms::Room, solver::Search, topology::Topology};
+
+    use super::{parse_maze, write_maze};
+
+    #[test]
+    fn maze_survives_a_round_trip() {
+        let config = MazeConfig {
+            entrances: 2,
+            goal: Goal::Farthest,
+            search: Search::AStar,
+            rooms: vec![Room { row: 6, column: 0, rows: 3, columns: 4, doors: 2 }],
+            ..Default::default()
+        };
+        let mut maze = Maze { ring_gap: 10, rings: 12, initial_divisions: 4, solution: Vec::new(), config };
+        let cells = maze.get_cells([11, 13, 15, 2]);
+
+        let text = write_maze(&maze, &cells);
+        let (loaded, loaded_cells) = parse_maze(&text).unwrap();
+
+        assert_eq!(loaded.solution, maze.solution);
+        assert_eq!(loaded.entrances(), maze.entrances());
+        assert_eq!(loaded.solutions(&loaded_cells), maze.solutions(&cells));
+        assert_eq!(loaded.config.rooms.len(), 1);
+        assert_eq!(write_maze(&loaded, &loaded_cells), text);
+        for ring in 0..maze.rows() {
+            for division in 0..maze.columns(ring) {
+                for neighbour in maze.neighbours(ring, division) {
+                    assert_eq!(loaded.linked(&loaded_cells, (ring, division), neighbour), maze.linked(&cells, (ring, division), neighbour));
+                }
+            }
+        }
+
+        assert!(parse_maze(&text.replace("rings 12", "rings 13")).is_err());
+        assert!(parse_maze(&text.replace("cells\n", "cells\nz")).is_err());
+        assert!(parse_maze(&text.replace("goal Farthest", "goal Nowhere")).is_err());
+        assert!(parse_maze(&text.replace("ring_gap 10", "ring_gap 4294967296")).is_err());
+        assert!(parse_maze(&text.replace("rooms 6,0,3,4,2", "rooms 6,0,3")).is_err());
+    }
+}
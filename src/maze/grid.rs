@@ -0,0 +1,124 @@
+use super::{cell::{Cell, Wall}, config::MazeConfig, topology::{Topology, Walls}};
+
+/// An orthogonal `width` x `height` maze of square cells.
+/// Rows play the part of rings: `inner_wall` is the top wall of a cell,
+/// `right_wall` its right wall and `outer_wall` the bottom wall of the last row.
+pub struct Grid {
+    pub cell_size: u32,
+    pub width: usize,
+    pub height: usize,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl Grid {
+
+    /// The top left corner of a cell
+    fn corner(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let margin = wall_width as f64;
+        let size = self.cell_size as f64;
+        (margin + column as f64 * size, margin + row as f64 * size)
+    }
+}
+
+impl Topology for Grid {
+
+    /// Carves a maze entered through the bottom left cell and exited through the top right cell
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+
+        if column + 1 < self.width {
+            neighbours.push((row, column + 1));
+        }
+        if column > 0 {
+            neighbours.push((row, column - 1));
+        }
+        if row > 0 {
+            neighbours.push((row - 1, column));
+        }
+        if row + 1 < self.height {
+            neighbours.push((row + 1, column));
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if row_a == row_b {
+            // Same row: the leftmost cell owns its right wall
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else {
+            // Different rows: the lower cell owns its top wall
+            ((row_a.max(row_b), column_a), Wall::Inner)
+        }
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            for (column, cell) in row_cells.iter().enumerate() {
+                let (left, top) = self.corner(row, column, wall_width);
+                let (right, bottom) = (left + size, top + size);
+
+                // Walls are extended by half their width so that corners are filled
+                if cell.inner_wall {
+                    lines.push(((left - mid_wall, top), (right + mid_wall, top)));
+                }
+                if cell.right_wall {
+                    lines.push(((right, top - mid_wall), (right, bottom + mid_wall)));
+                }
+                if row == self.height - 1 && cell.outer_wall {
+                    lines.push(((left - mid_wall, bottom), (right + mid_wall, bottom)));
+                }
+                if column == 0 {
+                    lines.push(((left, top - mid_wall), (left, bottom + mid_wall)));
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let half = self.cell_size as f64 / 2.0;
+        let (left, top) = self.corner(row, column, wall_width);
+        (left + half, top + half)
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let margin = 2 * wall_width;
+        (
+            self.cell_size * self.width as u32 + margin,
+            self.cell_size * self.height as u32 + margin,
+        )
+    }
+}
@@ -0,0 +1,166 @@
+use std::f64::consts::PI;
+
+use image::Rgb;
+
+use crate::utils::{math::TupleMath, pixels::Bitmap};
+use super::{cell::{Cell, Wall}, config::MazeConfig, topology::{extended, Topology, Walls}};
+
+/// A `width` x `height` maze of pointy-topped hexagons, with odd rows shifted right by half a cell.
+/// Each cell owns the walls towards its upper left (`inner_wall`), upper right (`inner_right_wall`)
+/// and right (`right_wall`) neighbours. `outer_wall` is the bottom edge of the last row.
+pub struct HexGrid {
+    /// The length of a side of a hexagon in pixels
+    pub cell_size: u32,
+    pub width: usize,
+    pub height: usize,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl HexGrid {
+
+    /// The column of the upper left neighbour, which may lie outside the grid
+    fn inner_column(&self, row: usize, column: usize) -> isize {
+        column as isize - (row.is_multiple_of(2)) as isize
+    }
+
+    /// The six corners clockwise from the upper right, in screen coordinates
+    fn vertices(&self, row: usize, column: usize, wall_width: u32) -> [(f64, f64); 6] {
+        let centre = self.cell_centre(row, column, wall_width);
+        let size = self.cell_size as f64;
+        std::array::from_fn(|i| {
+            let angle = PI / 3.0 * i as f64 - PI / 6.0;
+            centre.add((angle.cos(), angle.sin()).mul(size))
+        })
+    }
+}
+
+impl Topology for HexGrid {
+
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(6);
+
+        if column + 1 < self.width {
+            neighbours.push((row, column + 1));
+        }
+        if column > 0 {
+            neighbours.push((row, column - 1));
+        }
+
+        // Rows above and below share the same column offsets
+        let left = self.inner_column(row, column);
+        for adjacent_row in [row.checked_sub(1), Some(row + 1).filter(|&r| r < self.height)] {
+            let Some(adjacent_row) = adjacent_row else { continue };
+            for adjacent_column in [left, left + 1] {
+                if adjacent_column >= 0 && (adjacent_column as usize) < self.width {
+                    neighbours.push((adjacent_row, adjacent_column as usize));
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if row_a == row_b {
+            // Same row: the leftmost cell owns its right wall
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else {
+            // Different rows: the lower cell owns its upper left and upper right walls
+            let ((lower_row, lower_column), (_, upper_column)) = if row_a > row_b {
+                ((row_a, column_a), (row_b, column_b))
+            } else {
+                ((row_b, column_b), (row_a, column_a))
+            };
+            let wall = if self.inner_column(lower_row, lower_column) == upper_column as isize {
+                Wall::Inner
+            } else {
+                Wall::InnerRight
+            };
+            ((lower_row, lower_column), wall)
+        }
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let last_row = self.height - 1;
+        let last_column = self.width - 1;
+
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            for (column, cell) in row_cells.iter().enumerate() {
+                let [upper_right, lower_right, bottom, lower_left, upper_left, top] = self.vertices(row, column, wall_width);
+
+                let even = row.is_multiple_of(2);
+
+                let walls = [
+                    (cell.inner_wall, upper_left, top),
+                    (cell.inner_right_wall, top, upper_right),
+                    (cell.right_wall, upper_right, lower_right),
+                    // Boundary walls without a neighbour to own them
+                    (column == 0, lower_left, upper_left),
+                    (column == 0 && even && row != last_row, bottom, lower_left),
+                    (column == last_column && !even && row != last_row, lower_right, bottom),
+                    (row == last_row && cell.outer_wall, lower_right, bottom),
+                    (row == last_row && cell.outer_wall, bottom, lower_left),
+                ];
+
+                for (standing, begin, end) in walls {
+                    if standing {
+                        lines.push(extended(begin, end, mid_wall));
+                    }
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let size = self.cell_size as f64;
+        let cell_width = 3f64.sqrt() * size;
+        let offset = if row.is_multiple_of(2) { 0.5 } else { 1.0 };
+        (
+            wall_width as f64 + cell_width * (column as f64 + offset),
+            wall_width as f64 + size + 1.5 * size * row as f64,
+        )
+    }
+
+    fn fill_cell(&self, bitmap: &mut Bitmap, row: usize, column: usize, wall_width: u32, rgb: Rgb<u8>) {
+        bitmap.polygon(&self.vertices(row, column, wall_width), rgb);
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let size = self.cell_size as f64;
+        let margin = 2.0 * wall_width as f64;
+        let width = 3f64.sqrt() * size * (self.width as f64 + 0.5) + margin;
+        let height = size * (2.0 + 1.5 * (self.height as f64 - 1.0)) + margin;
+        (width.ceil() as u32, height.ceil() as u32)
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashSet, VecDeque};
+
+use brickadia::save::{Brick, BrickColor, Size};
+
+use crate::{metadata::assets::BrickAssets, utils::{brick::BLACK_BRICK, pixels::WHITE, sfc32::SFC32, walk::brick_pixels_at}};
+use super::{cell::Cell, topology::Topology};
+
+/// A cell on a given floor
+pub type Position = (usize, (usize, usize));
+
+/// A stack of mazes sharing one topology, joined by stairs between consecutive floors.
+/// The maze is entered on the bottom floor and solved on the top floor.
+pub struct Levels<T: Topology> {
+    /// The layout of every floor
+    pub topology: T,
+    /// At least one, which is why `Levels` is built through `new`
+    floors: usize,
+    /// The number of stairs between each pair of consecutive floors
+    pub stairs: usize,
+    /// The thickness of the slabs separating floors
+    pub slab_thickness: u32,
+    /// Cells with a stair up to the same cell on the floor above
+    pub stair_cells: Vec<Position>,
+    pub solution: Vec<Position>,
+}
+
+impl<T: Topology> Levels<T> {
+
+    /// Returns an error if there are no floors, since the maze is entered on the first and left on the last
+    pub fn new(topology: T, floors: usize, stairs: usize, slab_thickness: u32) -> Result<Self, String> {
+        if floors == 0 {
+            return Err("A maze needs at least one floor".to_string());
+        }
+        Ok(Self { topology, floors, stairs, slab_thickness, stair_cells: Vec::new(), solution: Vec::new() })
+    }
+
+    /// The bottom of a floor's walls, in position units
+    fn elevation(&self, floor: usize, wall_height: u32) -> i32 {
+        floor as i32 * 2 * (wall_height + self.slab_thickness) as i32
+    }
+
+    /// Carves every floor and joins consecutive floors with stairs
+    pub fn get_floors(&mut self, seed: [u32; 4]) -> Vec<Vec<Vec<Cell>>> {
+
+        let mut s_random = SFC32::new(seed);
+        let top = self.floors - 1;
+
+        let mut start = (0, 0);
+        let mut goal = (0, 0);
+
+        let floors: Vec<Vec<Vec<Cell>>> = (0..self.floors).map(|floor| {
+            let [a, b, c, d] = seed;
+            let mut cells = self.topology.get_cells([a, b, c, d.wrapping_add(floor as u32)]);
+
+            // Only the bottom floor is entered and only the top floor holds the goal
+            let exit = self.topology.exit();
+            if floor == 0 {
+                start = self.topology.solution().first().copied().unwrap_or(self.topology.entrance());
+            } else {
+                for (row, column) in self.topology.entrances() {
+                    cells[row][column].outer_wall = true;
+                }
+            }
+            if floor == top {
+                goal = self.topology.solution().last().copied().unwrap_or(exit);
+            } else if let Some(((row, column), wall)) = self.topology.exit_wall(&cells) {
+                *cells[row][column].wall_mut(wall) = true;
+            }
+            cells
+        }).collect();
+
+        // Cells without neighbours, such as those outside a mask, are not part of the maze
+        let positions: Vec<(usize, usize)> = (0..self.topology.rows())
+            .flat_map(|row| (0..self.topology.columns(row)).map(move |column| (row, column)))
+            .filter(|&(row, column)| !self.topology.neighbours(row, column).is_empty())
+            .collect();
+
+        self.stair_cells.clear();
+        for floor in 0..top {
+            let mut candidates = positions.clone();
+            s_random.shuffle(&mut candidates);
+            self.stair_cells.extend(candidates.into_iter().take(self.stairs.max(1)).map(|cell| (floor, cell)));
+        }
+
+        self.solution = self.solve(&floors, (0, start), (top, goal));
+
+        floors
+    }
+
+    /// Finds the shortest path between two positions, climbing stairs where needed
+    pub fn solve(&self, floors: &[Vec<Vec<Cell>>], from: Position, to: Position) -> Vec<Position> {
+
+        let stairs: HashSet<Position> = self.stair_cells.iter().copied().collect();
+
+        let mut previous: Vec<Vec<Vec<Option<Position>>>> = floors.iter()
+            .map(|cells| cells.iter().map(|row| vec![None; row.len()]).collect())
+            .collect();
+        previous[from.0][from.1.0][from.1.1] = Some(from);
+
+        let mut queue = VecDeque::from([from]);
+
+        while let Some((floor, cell)) = queue.pop_front() {
+            if (floor, cell) == to {
+                break;
+            }
+
+            let mut next: Vec<Position> = self.topology.neighbours(cell.0, cell.1)
+                .into_iter()
+                .filter(|&neighbour| self.topology.linked(&floors[floor], cell, neighbour))
+                .map(|neighbour| (floor, neighbour))
+                .collect();
+            if stairs.contains(&(floor, cell)) {
+                next.push((floor + 1, cell));
+            }
+            if floor > 0 && stairs.contains(&(floor - 1, cell)) {
+                next.push((floor - 1, cell));
+            }
+
+            for (floor_next, (row, column)) in next {
+                if previous[floor_next][row][column].is_none() {
+                    previous[floor_next][row][column] = Some((floor, cell));
+                    queue.push_back((floor_next, (row, column)));
+                }
+            }
+        }
+
+        if previous[to.0][to.1.0][to.1.1].is_none() {
+            return Vec::new();
+        }
+
+        let mut path = vec![to];
+        let mut position = to;
+        while position != from {
+            position = previous[position.0][position.1.0][position.1.1].unwrap();
+            path.push(position);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Builds every floor as its own layer of bricks, with slabs between floors and ramps joining them
+    pub fn generate(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, granularity: f64, solve: bool) -> Vec<Brick> {
+
+        let floors = self.get_floors(seed);
+        let factor = (1.0 / granularity).round().max(1.0) as u32;
+
+        let mut bricks = Vec::new();
+
+        for (floor, cells) in floors.iter().enumerate() {
+
+            let elevation = self.elevation(floor, wall_height);
+
+            let mut bitmap = self.topology.bitmap(cells, wall_width);
+
+            if solve {
+                let mut solution = bitmap.clone();
+                let path: Vec<(usize, usize)> = self.solution.iter()
+                    .filter(|&&(solution_floor, _)| solution_floor == floor)
+                    .map(|&(_, cell)| cell)
+                    .collect();
+                self.topology.draw_path(&mut solution, &path, wall_width);
+                solution.save(&format!("solution_{}", floor));
+            }
+
+            bricks.extend(brick_pixels_at(&mut bitmap, wall_height, elevation));
+
+            bitmap.downscale(factor);
+            bitmap.save(&format!("maze_{}", floor));
+
+            if floor > 0 {
+                // A slab beneath the floor, with holes above the stairs leading up to it
+                let mut slab = self.topology.footprint(wall_width);
+                for &(_, (row, column)) in self.stair_cells.iter().filter(|&&(stair_floor, _)| stair_floor == floor - 1) {
+                    let centre = self.topology.cell_centre(row, column, wall_width);
+                    slab.disc(centre, self.topology.cell_radius(row, column, wall_width), WHITE);
+                }
+                bricks.extend(brick_pixels_at(&mut slab, self.slab_thickness, elevation - 2 * self.slab_thickness as i32));
+            }
+        }
+
+        for &(floor, (row, column)) in &self.stair_cells {
+            bricks.push(self.ramp(floor, (row, column), wall_width, wall_height));
+        }
+
+        bricks
+    }
+
+    /// A wedge rising from a floor to the top of the slab above it, fitted inside the cell
+    fn ramp(&self, floor: usize, (row, column): (usize, usize), wall_width: u32, wall_height: u32) -> Brick {
+
+        let (x, y) = self.topology.cell_centre(row, column, wall_width);
+
+        // The side of the square inscribed in the hole through the slab
+        let side = (self.topology.cell_radius(row, column, wall_width) * std::f64::consts::SQRT_2).floor().max(1.0) as u32;
+        let rise = wall_height + self.slab_thickness;
+
+        Brick {
+            color: BrickColor::Unique(BLACK_BRICK),
+            size: Size::Procedural(side, side, rise),
+            asset_name_index: BrickAssets::MicroWedge.index() as u32,
+            position: ((2.0 * x).round() as i32, (2.0 * y).round() as i32, self.elevation(floor, wall_height) + rise as i32),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::MazeConfig, grid::Grid, mask::MaskGrid, maze::Maze, topology::Topology};
+
+    use super::Levels;
+
+    #[test]
+    fn levels_solution_climbs_stairs() {
+        let grid = Grid { cell_size: 10, width: 12, height: 8, solution: Vec::new(), config: MazeConfig::default() };
+        let mut levels = Levels::new(grid, 3, 2, 1).unwrap();
+        let floors = levels.get_floors([11, 13, 15, 2]);
+
+        assert_eq!(levels.solution.first().map(|&(floor, _)| floor), Some(0));
+        assert_eq!(levels.solution.last().map(|&(floor, _)| floor), Some(2));
+
+        for pair in levels.solution.windows(2) {
+            let ((floor, cell), (next_floor, next)) = (pair[0], pair[1]);
+            if floor == next_floor {
+                assert!(levels.topology.linked(&floors[floor], cell, next));
+            } else {
+                assert_eq!(cell, next);
+                assert!(levels.stair_cells.contains(&(floor.min(next_floor), cell)));
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_top_floor_opens_onto_the_centre() {
+        let maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
+        let mut levels = Levels::new(maze, 3, 2, 1).unwrap();
+        let floors = levels.get_floors([11, 13, 15, 2]);
+
+        for (floor, cells) in floors.iter().enumerate() {
+            assert_eq!(levels.topology.exit_wall(cells).is_some(), floor == 2, "Floor {floor}");
+        }
+        assert_eq!(levels.solution.last().map(|&(floor, (ring, _))| (floor, ring)), Some((2, 0)));
+    }
+
+    #[test]
+    fn stairs_stay_inside_the_mask() {
+        // A ring of cells around a hole
+        let mask: Vec<Vec<bool>> = (0..8).map(|row| (0..8).map(|column| !(2..6).contains(&row) || !(2..6).contains(&column)).collect()).collect();
+        let grid = MaskGrid { cell_size: 10, mask: mask.clone(), solution: Vec::new(), config: MazeConfig::default() };
+        let mut levels = Levels::new(grid, 3, 20, 1).unwrap();
+        levels.get_floors([11, 13, 15, 2]);
+
+        assert_eq!(levels.stair_cells.len(), 40);
+        assert!(levels.stair_cells.iter().all(|&(_, (row, column))| mask[row][column]));
+        assert!(!levels.solution.is_empty());
+    }
+
+    #[test]
+    fn levels_need_a_floor() {
+        let grid = Grid { cell_size: 10, width: 12, height: 8, solution: Vec::new(), config: MazeConfig::default() };
+        assert!(Levels::new(grid, 0, 2, 1).is_err());
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+
+use image::RgbImage;
+
+use crate::utils::pixels::{Bitmap, BLACK};
+use super::{cell::{Cell, Wall}, config::MazeConfig, topology::{Topology, Walls}};
+
+/// An orthogonal maze of square cells cut out of a silhouette.
+/// Only cells where `mask` is set belong to the maze, and its outer walls follow the edge of the mask.
+/// Like `Grid`, `inner_wall` is the top wall of a cell and `right_wall` its right wall,
+/// while `outer_wall` is the bottom wall of a cell with no cell beneath it.
+pub struct MaskGrid {
+    pub cell_size: u32,
+    /// Whether each cell, indexed `[row][column]`, belongs to the maze
+    pub mask: Vec<Vec<bool>>,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl MaskGrid {
+
+    /// Samples `image` at the centre of each of `columns` cells across it.
+    /// Dark pixels are inside the maze, and only the largest connected region of them is kept.
+    /// Returns an error if no cell is dark, or the image is too short to hold a row of cells.
+    pub fn from_image(image: &RgbImage, columns: usize, cell_size: u32, config: MazeConfig) -> Result<Self, String> {
+
+        let (width, height) = image.dimensions();
+        if width == 0 {
+            return Err("The mask image is empty".to_string());
+        }
+        let scale = width as f64 / columns.max(1) as f64;
+        let rows = (height as f64 / scale).floor() as usize;
+
+        let mask: Vec<Vec<bool>> = (0..rows).map(|row| {
+            (0..columns).map(|column| {
+                let x = (((column as f64 + 0.5) * scale) as u32).min(width - 1);
+                let y = (((row as f64 + 0.5) * scale) as u32).min(height - 1);
+                let [r, g, b] = image.get_pixel(x, y).0;
+                (r as u32 + g as u32 + b as u32) < 3 * 128
+            }).collect()
+        }).collect();
+
+        if !mask.iter().flatten().any(|&inside| inside) {
+            return Err(format!("No cell of the {}x{} image is dark enough to be part of the maze", width, height));
+        }
+
+        Ok(Self { cell_size, mask: largest_region(mask), solution: Vec::new(), config })
+    }
+
+    pub fn from_bitmap(bitmap: &Bitmap, columns: usize, cell_size: u32, config: MazeConfig) -> Result<Self, String> {
+        Self::from_image(&bitmap.image, columns, cell_size, config)
+    }
+
+    fn contains(&self, row: isize, column: isize) -> bool {
+        row >= 0 && column >= 0 && self.mask.get(row as usize)
+            .and_then(|columns| columns.get(column as usize))
+            .copied()
+            .unwrap_or(false)
+    }
+
+    /// The top left corner of a cell
+    fn corner(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let margin = wall_width as f64;
+        let size = self.cell_size as f64;
+        (margin + column as f64 * size, margin + row as f64 * size)
+    }
+
+    fn width(&self) -> usize {
+        self.mask.first().map_or(0, Vec::len)
+    }
+}
+
+/// Keeps the largest 4-connected region of a mask, so that every cell can be reached
+fn largest_region(mask: Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+
+    let mut region: Vec<Vec<Option<usize>>> = mask.iter().map(|row| vec![None; row.len()]).collect();
+    let mut sizes = Vec::new();
+
+    for row in 0..mask.len() {
+        for column in 0..mask[row].len() {
+            if !mask[row][column] || region[row][column].is_some() {
+                continue;
+            }
+
+            let label = sizes.len();
+            let mut size = 0;
+            region[row][column] = Some(label);
+            let mut queue = VecDeque::from([(row, column)]);
+
+            while let Some((row, column)) = queue.pop_front() {
+                size += 1;
+                let adjacent = [
+                    (row.wrapping_sub(1), column),
+                    (row + 1, column),
+                    (row, column.wrapping_sub(1)),
+                    (row, column + 1),
+                ];
+                for (row, column) in adjacent {
+                    if mask.get(row).and_then(|columns| columns.get(column)) == Some(&true) && region[row][column].is_none() {
+                        region[row][column] = Some(label);
+                        queue.push_back((row, column));
+                    }
+                }
+            }
+
+            sizes.push(size);
+        }
+    }
+
+    let largest = (0..sizes.len()).max_by_key(|&label| sizes[label]);
+
+    region.into_iter()
+        .map(|row| row.into_iter().map(|label| label.is_some() && label == largest).collect())
+        .collect()
+}
+
+impl Topology for MaskGrid {
+
+    /// Carves a maze entered through the bottom left cell of the mask and exited through its top right cell
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.mask.len()
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width()
+    }
+
+    /// Cells outside the mask are visited from the start, so they are never carved
+    fn new_cells(&self) -> Vec<Vec<Cell>> {
+        self.mask.iter()
+            .map(|row| row.iter().map(|&inside| Cell { visited: !inside, ..Cell::new() }).collect())
+            .collect()
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+
+        if !self.mask[row][column] {
+            return neighbours;
+        }
+
+        let (row, column) = (row as isize, column as isize);
+        for (row, column) in [(row, column + 1), (row, column - 1), (row - 1, column), (row + 1, column)] {
+            if self.contains(row, column) {
+                neighbours.push((row as usize, column as usize));
+            }
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if row_a == row_b {
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else {
+            ((row_a.max(row_b), column_a), Wall::Inner)
+        }
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        let mut lines = Vec::new();
+
+        for (row, (row_cells, row_mask)) in cells.iter().zip(&self.mask).enumerate() {
+            for (column, (cell, &inside)) in row_cells.iter().zip(row_mask).enumerate() {
+                if !inside {
+                    continue;
+                }
+
+                let (left, top) = self.corner(row, column, wall_width);
+                let (right, bottom) = (left + size, top + size);
+
+                // Walls towards cells outside the mask are never opened, except for the exit and entrance
+                if cell.inner_wall {
+                    lines.push(((left - mid_wall, top), (right + mid_wall, top)));
+                }
+                if cell.right_wall {
+                    lines.push(((right, top - mid_wall), (right, bottom + mid_wall)));
+                }
+                if cell.outer_wall && !self.contains(row as isize + 1, column as isize) {
+                    lines.push(((left - mid_wall, bottom), (right + mid_wall, bottom)));
+                }
+                if !self.contains(row as isize, column as isize - 1) {
+                    lines.push(((left, top - mid_wall), (left, bottom + mid_wall)));
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let half = self.cell_size as f64 / 2.0;
+        let (left, top) = self.corner(row, column, wall_width);
+        (left + half, top + half)
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let margin = 2 * wall_width;
+        (
+            self.cell_size * self.width() as u32 + margin,
+            self.cell_size * self.rows() as u32 + margin,
+        )
+    }
+
+    /// The lowest row of the mask, entered from below through its leftmost cell
+    fn entrance(&self) -> (usize, usize) {
+        (0..self.rows()).rev()
+            .find_map(|row| self.mask[row].iter().position(|&inside| inside).map(|column| (row, column)))
+            .unwrap_or((self.rows() - 1, 0))
+    }
+
+    /// The lowest cell of each column, from left to right, so that entrances are never opened into a hole
+    fn boundary(&self) -> Vec<(usize, usize)> {
+        (0..self.width())
+            .filter_map(|column| (0..self.rows()).rev().find(|&row| self.mask[row][column]).map(|row| (row, column)))
+            .collect()
+    }
+
+    /// The highest row of the mask, left from above through its rightmost cell
+    fn exit(&self) -> (usize, usize) {
+        (0..self.rows())
+            .find_map(|row| self.mask[row].iter().rposition(|&inside| inside).map(|column| (row, column)))
+            .unwrap_or((0, self.width() - 1))
+    }
+
+    fn footprint(&self, wall_width: u32) -> Bitmap {
+        let (width, height) = self.dimensions(wall_width);
+        let mut bitmap = Bitmap::with_dimensions(width, height);
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        for row in 0..self.rows() {
+            for column in 0..self.width() {
+                if self.mask[row][column] {
+                    let (left, top) = self.corner(row, column, wall_width);
+                    let (begin, end) = ((left - mid_wall).floor(), (left + size + mid_wall).ceil());
+                    let (upper, lower) = ((top - mid_wall).floor(), (top + size + mid_wall).ceil());
+                    bitmap.filled_rectangle(&[(begin as i32, upper as i32), (end as i32 + 1, lower as i32 + 1)], BLACK);
+                }
+            }
+        }
+
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+    use strum::IntoEnumIterator;
+
+    use crate::maze::{algorithms::Algorithm, config::MazeConfig, solver::distance_field, topology::Topology};
+
+    use super::MaskGrid;
+
+    #[test]
+    fn mask_carves_a_perfect_maze_inside_the_silhouette() {
+        // A ring and a stray dot, which is not connected to the ring and is dropped
+        let image = RgbImage::from_fn(40, 40, |x, y| {
+            let distance = ((x as f64 - 19.5).powi(2) + (y as f64 - 19.5).powi(2)).sqrt();
+            if (8.0..18.0).contains(&distance) || (x < 2 && y < 2) { Rgb([0, 0, 0]) } else { Rgb([255, 255, 255]) }
+        });
+
+        for algorithm in Algorithm::iter() {
+            let mut mask = MaskGrid::from_image(&image, 20, 10, MazeConfig { algorithm, ..Default::default() }).unwrap();
+            assert!(!mask.mask[0][0] && !mask.mask[10][10]);
+
+            let cells = mask.get_cells([11, 13, 15, 2]);
+
+            let inside: Vec<(usize, usize)> = (0..mask.rows())
+                .flat_map(|row| (0..mask.columns(row)).map(move |column| (row, column)))
+                .filter(|&(row, column)| mask.mask[row][column])
+                .collect();
+            let passages: usize = inside.iter()
+                .map(|&cell| mask.neighbours(cell.0, cell.1).into_iter().filter(|&next| cell < next && mask.linked(&cells, cell, next)).count())
+                .sum();
+            assert_eq!(passages, inside.len() - 1, "{algorithm}: the maze is not a spanning tree");
+
+            let distances = distance_field(&mask, &cells, mask.entrance());
+            assert!(inside.iter().all(|&(row, column)| distances[row][column].is_some()), "{algorithm}: a cell cannot be reached");
+            assert_eq!(mask.solution.last(), Some(&mask.exit()), "{algorithm}");
+        }
+    }
+
+    #[test]
+    fn images_without_cells_inside_are_rejected() {
+        let white = RgbImage::from_pixel(40, 40, Rgb([255, 255, 255]));
+        assert!(MaskGrid::from_image(&white, 20, 10, MazeConfig::default()).is_err());
+
+        // Too short for a single row of cells
+        let short = RgbImage::from_pixel(40, 1, Rgb([0, 0, 0]));
+        assert!(MaskGrid::from_image(&short, 20, 10, MazeConfig::default()).is_err());
+        assert!(MaskGrid::from_image(&RgbImage::new(0, 0), 20, 10, MazeConfig::default()).is_err());
+    }
+}
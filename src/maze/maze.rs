@@ -1,234 +1,188 @@
-use brickadia::save::Brick;
+use image::Rgb;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::utils::{pixels::BLACK, walk::brick_pixels};
-
-use crate::utils::{pixels::{Bitmap, RED}, math::TupleMath, sfc32::SFC32};
-use super::cell::Cell;
-
-
-const MAX_RING: usize = 9;
+use crate::utils::{math::TupleMath, pixels::{Bitmap, BLACK}};
+use super::{cell::{Cell, Wall}, config::{Goal, MazeConfig}, rooms::Room, topology::{Topology, Walls}};
 
+/// A circular maze of `rings` concentric rings around a centre of `initial_divisions` cells.
+/// Each ring is divided so that its cells are about as wide as they are deep,
+/// so an outer cell may sit across two inner cells: it owns the wall to the one it starts on
+/// as `inner_wall`, and the wall to the next as `inner_right_wall`.
 pub struct Maze {
     pub ring_gap: u32,
     pub rings: usize,
+    /// The number of cells in the centre, which is only a minimum for the other rings:
+    /// once their circumference holds more cells, it sets their divisions instead
     pub initial_divisions: usize,
     pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
 }
 impl Maze {
 
+    /// The number of cells whose arc along the middle of the ring is closest to `ring_gap`,
+    /// never fewer than in the centre so that rings only widen outward
     pub fn divisions_in_ring(&self, ring: usize) -> usize {
-        let ring = if  ring > MAX_RING {MAX_RING + ring % 2} else {ring};
-        self.initial_divisions << (ring >> 1)
+        let cells_per_circumference = (std::f64::consts::TAU * (ring as f64 + 0.5)).round() as usize;
+        cells_per_circumference.max(self.initial_divisions)
     }
 
-    fn open_wall_between(
-        &self, 
-        cells: &mut [Vec<Cell>],
-        (ring_a, div_a): (usize, usize),
-        (ring_b, div_b): (usize, usize),
-    ) {
-        if ring_a == ring_b {
-            // Same ring: Determine the preceding division and open its right wall
-            let next = (div_a + 1) % self.divisions_in_ring(ring_a);
-            let target = if next == div_b { div_a } else { div_b };
-            cells[ring_a][target].right_wall = false;
-    
-        } else {
-            // Different rings: Open the inner wall of the outermost ring cell
-            let (outer_ring, outer_division) = if ring_a > ring_b {
-                (ring_a, div_a)
-            } else {
-                (ring_b, div_b)
-            };
-    
-            // Open the inner wall of the corresponding cell in the outer ring
-            cells[outer_ring][outer_division].inner_wall = false;
-        }
+    /// The divisions of the inner ring under a division: the one under its start,
+    /// and the next one if the division reaches over it
+    fn inner_divisions(&self, ring: usize, division: usize) -> (usize, Option<usize>) {
+        let divisions = self.divisions_in_ring(ring);
+        let inner_divisions = self.divisions_in_ring(ring - 1);
+
+        // Compared in units of 1 / (divisions * inner_divisions) of a turn
+        let first = division * inner_divisions / divisions;
+        let overlaps_next = (first + 1) * divisions < (division + 1) * inner_divisions;
+
+        (first, overlaps_next.then_some(first + 1))
     }
 
-    /// Computes and returns `Vec<(ring, division)>` of all unvisited neighbours
-    fn unvisited_neighbours(
-        &self, 
-        cells: &[Vec<Cell>],
-        ring: usize, 
-        division: usize, 
-    ) -> Vec<(usize, usize)> {
-        let mut unvisited = Vec::with_capacity(4);
-        let ring_divisions = self.divisions_in_ring(ring);
-    
-        // Check the next clockwise and anticlockwise neighbors in the same ring
-        let clockwise = (division + 1) % ring_divisions;
-        if !cells[ring][clockwise].visited {
-            unvisited.push((ring, clockwise));
-        }
-    
-        let anticlockwise = (division + ring_divisions - 1) % ring_divisions;
-        if !cells[ring][anticlockwise].visited {
-            unvisited.push((ring, anticlockwise));
-        }
-    
-        // Check neighboring division on the previous ring if not on the innermost ring
-        if ring > 0 {
-            let inner_divisions = self.divisions_in_ring(ring - 1);
-            let mapped_inner_division = (division * inner_divisions) / ring_divisions;
-            if !cells[ring - 1][mapped_inner_division].visited {
-                unvisited.push((ring - 1, mapped_inner_division));
-            }
-        }
-    
-        // Check the neighboring divisions on the next ring if not on the outermost ring
-        if ring < self.rings - 1 {
-            let outer_divisions = self.divisions_in_ring(ring + 1);
-            let mapped_outer_division = (division * outer_divisions) / ring_divisions;
-            if !cells[ring + 1][mapped_outer_division].visited {
-                unvisited.push((ring + 1, mapped_outer_division));
-            }
-        }
-    
-        unvisited
+}
+
+impl Topology for Maze {
+
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
     }
 
-    pub fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
-
-        let mut cells: Vec<Vec<Cell>> = (0..self.rings).map(|ring| {vec![Cell::new(); self.divisions_in_ring(ring)]}).collect();
-
-        // A closure for generating random floats (f64) using bitshift operations
-        let mut s_random = SFC32::new(seed);
-    
-        // Mark all cells in the centre as visited and remove their radial walls
-        cells[0].iter_mut().for_each(|cell| {
-            cell.right_wall = false;
-            cell.visited = true;
-        });
-    
-        // Mark the starting cell in the outermost ring as visited and remove its outer wall
-        cells[self.rings - 1][0].visited = true;
-        cells[self.rings - 1][0].outer_wall = false;
-    
-        let mut centre_ring_division = 0;
-    
-        // Begin with outermost cell
-        let mut ring = self.rings - 1;
-        let mut division = 0;
-    
-        // Backtracking queue for maze generation
-        let mut backtrack_path = vec![];
-
-        loop {
-            // Randomly choose an unvisited neighbor of the current cell
-            let unvisited: Vec<(usize, usize)> = self.unvisited_neighbours(&cells, ring, division);
-    
-            if !unvisited.is_empty() {
-
-                backtrack_path.push((ring, division));
-
-                // Pick a random unvisited neighboring cell
-                let neighbour_index: usize = s_random.rand_between(0, unvisited.len());
-                let (next_ring, next_division) = unvisited[neighbour_index];
-    
-                // Open the wall between the current cell and the chosen neighboring cell
-                self.open_wall_between(&mut cells, (ring, division), (next_ring, next_division));
-    
-                // Move to the neighboring cell and mark it as visited
-                ring = next_ring;
-                division = next_division;
-                cells[ring][division].visited = true;
-    
-                // Record the centre and the final division in the solution
-                if ring == 1 {
-                    centre_ring_division = division;
-                    self.solution = backtrack_path.clone();
-                    self.solution.push((ring, division));
-                    self.solution.push((0, division));
-                }
+    /// The cells in the centre are visited from the start, and their radial walls removed
+    fn new_cells(&self) -> Vec<Vec<Cell>> {
+        (0..self.rings)
+            .map(|ring| {
+                let cell = if ring == 0 { Cell { visited: true, right_wall: false, ..Cell::new() } } else { Cell::new() };
+                vec![cell; self.divisions_in_ring(ring)]
+            })
+            .collect()
+    }
 
-            } else if let Some((prev_ring, prev_division)) = backtrack_path.pop() {
-                // If there are no unvisited neighbors, backtrack to the previous cell
-                ring = prev_ring;
-                division = prev_division;
+    /// Opens the centre from the last cell carved in ring 1, whatever the goal. The solution ends in the
+    /// centre, beneath that cell, unless the goal lies elsewhere.
+    fn open_exit(&self, cells: &mut [Vec<Cell>], order: &[(usize, usize)]) -> Option<(usize, usize)> {
+        let centre_ring_division = order.iter().rev().find(|&&(ring, _)| ring == 1).map_or(0, |&(_, division)| division);
+        cells[1][centre_ring_division].inner_wall = false;
+        let (centre_division, _) = self.inner_divisions(1, centre_ring_division);
 
-            } else {
-                // No unvisited neighbors and no cells to backtrack to
-                // Assume the maze is completed
-                break;
-            }
+        match self.config.goal {
+            Goal::Exit | Goal::Centre => Some((0, centre_division)),
+            Goal::Random | Goal::Farthest => None,
         }
-    
-        // Remove the inner wall at the centre to open it up
-        cells[1][centre_ring_division].inner_wall = false;
+    }
 
-        cells
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
 
+    /// The wall opened between ring 1 and the centre
+    fn exit_wall(&self, cells: &[Vec<Cell>]) -> Option<((usize, usize), Wall)> {
+        (0..self.divisions_in_ring(1))
+            .flat_map(|division| self.neighbours(1, division).into_iter().filter(|&(ring, _)| ring == 0).map(move |centre| ((1, division), centre)))
+            .find(|&(cell, centre)| self.linked(cells, cell, centre))
+            .map(|(cell, centre)| self.shared_wall(cell, centre))
     }
 
-    pub fn draw_solution(&self, bitmap: &Bitmap, centre: (f64, f64)) {
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
 
-        let mut bitmap = bitmap.clone();
+    fn rows(&self) -> usize {
+        self.rings
+    }
 
-        if self.solution.is_empty() {
-            println!("ERROR: Empty solution!");
-            return;
-        }
+    fn columns(&self, ring: usize) -> usize {
+        self.divisions_in_ring(ring)
+    }
 
-        let mut prev: Option<(f64, f64)> = None;
-        let stroke_width = 1;
-    
-        for &(ring, division) in &self.solution {
+    fn neighbours(&self, ring: usize, division: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+        let ring_divisions = self.divisions_in_ring(ring);
 
-            let angle_per_division = 2.0 * std::f64::consts::PI / self.divisions_in_ring(ring) as f64;
+        // The next clockwise and anticlockwise neighbors in the same ring
+        neighbours.push((ring, (division + 1) % ring_divisions));
+        neighbours.push((ring, (division + ring_divisions - 1) % ring_divisions));
 
-            let angular_offset = angle_per_division * division as f64 + angle_per_division / 2.0;
-            let radial_offset = self.ring_gap as f64 * ring as f64 + self.ring_gap as f64 / 2.0;
+        // Neighboring divisions on the previous ring if not on the innermost ring
+        if ring > 0 {
+            let (first, next) = self.inner_divisions(ring, division);
+            neighbours.push((ring - 1, first));
+            neighbours.extend(next.map(|next| (ring - 1, next)));
+        }
 
-            let pole = (angular_offset.cos(), angular_offset.sin()); // UNIT CIRCLE POS [-1, 1]
-            let point = centre.add(pole.mul(radial_offset)); // CARTESIAN COORDS
+        // Neighboring divisions on the next ring which overlap this division
+        if ring < self.rings - 1 {
+            let outer_divisions = self.divisions_in_ring(ring + 1);
+            let first = division * outer_divisions / ring_divisions;
+            let last = ((division + 1) * outer_divisions).div_ceil(ring_divisions);
+            neighbours.extend((first..last).map(|outer_division| (ring + 1, outer_division)));
+        }
 
-            if let Some(prev) = prev {
-                bitmap.line(prev, point, RED, stroke_width);
-            }
+        neighbours
+    }
 
-            prev = Some(point);
+    fn shared_wall(&self, (ring_a, div_a): (usize, usize), (ring_b, div_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if ring_a == ring_b {
+            // Same ring: The preceding division owns the radial wall
+            let next = (div_a + 1) % self.divisions_in_ring(ring_a);
+            let target = if next == div_b { div_a } else { div_b };
+            ((ring_a, target), Wall::Right)
 
+        } else {
+            // Different rings: The cell in the outer ring owns the inner walls
+            let ((outer_ring, outer_division), inner_division) = if ring_a > ring_b { ((ring_a, div_a), div_b) } else { ((ring_b, div_b), div_a) };
+            let (first, _) = self.inner_divisions(outer_ring, outer_division);
+            let wall = if inner_division == first { Wall::Inner } else { Wall::InnerRight };
+            ((outer_ring, outer_division), wall)
         }
-
-        bitmap.save("solution");
     }
-    
-    pub fn arcs_and_walls(
+
+    fn arcs_and_walls(
         &self,
-        cells: Vec<Vec<Cell>>,
+        cells: &[Vec<Cell>],
         wall_width: u32, // STROKE WIDTH
-        centre: (f64, f64),
-    ) -> (Vec<(f64, f64, f64)>, Vec<((f64, f64), (f64, f64))>) {
+    ) -> Walls {
         let mid_wall = (wall_width / 2) as f64;
-    
+        let centre = self.centre(wall_width);
+
         (0..self.rings)
         .into_par_iter()
         .map(|ring| {
 
             let mut ring_arcs = Vec::new();
             let mut ring_lines = Vec::new();
-    
+
             let outermost_ring = ring == self.rings - 1;
-    
+
             let divisions = self.divisions_in_ring(ring);
             let arc_angle = 2.0 * std::f64::consts::PI / divisions as f64;
-    
+
             let inner_wall_radius = self.ring_gap as f64 * ring as f64;
             let outer_wall_radius = inner_wall_radius + self.ring_gap as f64;
-    
+
             for division in 0..divisions {
                 let cell = &cells[ring][division];
-    
+
                 let angle_beginning = arc_angle * division as f64;
                 let angle_ending = angle_beginning + arc_angle;
-    
-                // Inner rings
-                if cell.inner_wall && inner_wall_radius != 0.0 {
-                    ring_arcs.push((inner_wall_radius, angle_beginning, angle_ending));
+
+                // Inner rings, split where the division reaches over a second inner division
+                if ring > 0 {
+                    let (first, next) = self.inner_divisions(ring, division);
+                    let angle_split = if next.is_some() {
+                        2.0 * std::f64::consts::PI * (first + 1) as f64 / self.divisions_in_ring(ring - 1) as f64
+                    } else {
+                        angle_ending
+                    };
+                    if cell.inner_wall {
+                        ring_arcs.push((inner_wall_radius, angle_beginning, angle_split));
+                    }
+                    if next.is_some() && cell.inner_right_wall {
+                        ring_arcs.push((inner_wall_radius, angle_split, angle_ending));
+                    }
                 }
-    
+
                 // Radial walls
                 if cell.right_wall {
                     let pole = (angle_ending.cos(), angle_ending.sin()); // UNIT CIRCLE POS [-1, 1]
@@ -236,7 +190,7 @@ impl Maze {
                     let p2 = centre.add(pole.mul(outer_wall_radius + mid_wall));
                     ring_lines.push((p1, p2));
                 }
-    
+
                 // Outermost ring
                 if outermost_ring && cell.outer_wall {
                     ring_arcs.push((outer_wall_radius, angle_beginning, angle_ending));
@@ -257,41 +211,86 @@ impl Maze {
         )
     }
 
-    /// A detail value closer to 1 leads to a more granular approximation
-    pub fn generate(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, granularity: f64, solve: bool) -> Vec<Brick> { // [11, 12, 15, 2];
+    /// The annular sector spanning `rows` rings outward from `row`, between the angles
+    /// of divisions `column` and `column + columns` of its innermost ring
+    fn room_cells(&self, room: &Room) -> Vec<(usize, usize)> {
+        let inner_divisions = self.divisions_in_ring(room.row.min(self.rings - 1)) as f64;
+        let (begin, end) = (room.column as f64 / inner_divisions, (room.column + room.columns) as f64 / inner_divisions);
+
+        (room.row..(room.row + room.rows).min(self.rings))
+            .flat_map(|ring| {
+                let divisions = self.divisions_in_ring(ring);
+                (0..divisions)
+                    .filter(move |&division| (begin..end).contains(&((division as f64 + 0.5) / divisions as f64)))
+                    .map(move |division| (ring, division))
+            })
+            .collect()
+    }
+
+    fn cell_centre(&self, ring: usize, division: usize, wall_width: u32) -> (f64, f64) {
+
+        let angle_per_division = 2.0 * std::f64::consts::PI / self.divisions_in_ring(ring) as f64;
 
-        let cells = self.get_cells(seed);
+        let angular_offset = angle_per_division * division as f64 + angle_per_division / 2.0;
+        let radial_offset = self.ring_gap as f64 * ring as f64 + self.ring_gap as f64 / 2.0;
 
+        let pole = (angular_offset.cos(), angular_offset.sin()); // UNIT CIRCLE POS [-1, 1]
+        self.centre(wall_width).add(pole.mul(radial_offset)) // CARTESIAN COORDS
+    }
+
+    /// The annular sector between the arcs and radial walls of the cell
+    fn fill_cell(&self, bitmap: &mut Bitmap, ring: usize, division: usize, wall_width: u32, rgb: Rgb<u8>) {
+        let angle_per_division = 2.0 * std::f64::consts::PI / self.divisions_in_ring(ring) as f64;
+        let inner_radius = self.ring_gap as f64 * ring as f64;
+        let angle_begin = angle_per_division * division as f64;
+        bitmap.annular_sector(self.centre(wall_width), inner_radius, inner_radius + self.ring_gap as f64, angle_begin, angle_begin + angle_per_division, rgb);
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
         let radius = self.ring_gap * self.rings as u32 + wall_width;
-        let centre = (radius as f64, radius as f64);
+        (2 * radius, 2 * radius)
+    }
 
-        let (arcs, lines) = self.arcs_and_walls(cells, wall_width, centre);
+    /// The disc enclosed by the outermost ring
+    fn footprint(&self, wall_width: u32) -> Bitmap {
+        let (width, height) = self.dimensions(wall_width);
+        let mut bitmap = Bitmap::with_dimensions(width, height);
+        let radius = (self.ring_gap * self.rings as u32 + wall_width / 2) as f64;
+        bitmap.disc(self.centre(wall_width), radius, BLACK);
+        bitmap
+    }
+}
 
-        let image_width = 2 * radius as usize;
-        let mut bitmap = Bitmap::new(image_width);
-        
-        for (radius, angle_begin, angle_end) in arcs {
-            bitmap.arc(centre, radius, angle_begin, angle_end, BLACK, wall_width);
-        }
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::MazeConfig, topology::Topology};
+    use crate::utils::walk::brick_pixels;
 
-        for (begin, end) in lines {
-            bitmap.line(begin, end, BLACK, wall_width);
-        }
+    use super::Maze;
 
-        if solve {
-            self.draw_solution(&bitmap, centre);
-        }
+    #[test]
+    fn rings_keep_cells_square() {
+        let maze = Maze { ring_gap: 10, rings: 60, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
 
-        let bricks: Vec<Brick> = brick_pixels(&mut bitmap, wall_height);
+        for ring in 1..maze.rings {
+            let width = std::f64::consts::TAU * (ring as f64 + 0.5) / maze.divisions_in_ring(ring) as f64;
+            assert!((width - 1.0).abs() < 0.25, "ring {ring} cells are {width} gaps wide");
+
+            for division in 0..maze.divisions_in_ring(ring) {
+                for (row, column) in maze.neighbours(ring, division) {
+                    assert!(maze.neighbours(row, column).contains(&(ring, division)), "({ring}, {division}) and ({row}, {column})");
+                }
+            }
+        }
+    }
 
-        let factor = (1.0 / granularity).round().max(1.0) as u32;
-        bitmap.downscale(factor);
-        
+    #[test]
+    fn analytic_bricks_are_fewer_than_rasterized() {
+        let mut maze = Maze { ring_gap: 10, rings: 30, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
+        let cells = maze.get_cells([11, 13, 15, 2]);
 
-        bitmap.save("maze");
-        
-        bricks
-         
+        let rasterized = brick_pixels(&mut maze.bitmap(&cells, 5), 1).len();
+        let analytic = maze.analytic_bricks(&cells, 5, 1).len();
+        assert!(analytic * 2 < rasterized, "{analytic} analytic bricks against {rasterized} rasterized");
     }
-    
 }
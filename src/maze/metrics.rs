@@ -0,0 +1,162 @@
+use super::{braid::degree, cell::Cell, solver::distance_field, topology::Topology};
+
+/// The smallest change in heading, in radians, which counts as a turn along the solution
+pub const TURN_ANGLE: f64 = std::f64::consts::FRAC_PI_4;
+
+/// Statistics describing the texture of a carved maze, used to compare seeds and algorithms
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub cells: usize,
+    /// The number of cells along the solution
+    pub solution_length: usize,
+    /// Cells with a single passage
+    pub dead_ends: usize,
+    pub dead_end_ratio: f64,
+    /// The number of cells with each number of passages, indexed by that number
+    pub degrees: Vec<usize>,
+    pub passages: usize,
+    /// Passages between rows, the rings of a circular maze, rather than along them
+    pub radial_passages: usize,
+    /// The average number of cells in a corridor, a run of cells with exactly two passages
+    pub river_factor: f64,
+    /// The fraction of steps along the solution which change direction
+    pub turn_ratio: f64,
+    /// The cell furthest from the start of the solution, and its distance in steps
+    pub deepest_cell: (usize, usize),
+    pub deepest_distance: usize,
+}
+
+impl Metrics {
+
+    pub fn new<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], solution: &[(usize, usize)]) -> Self {
+
+        // Cells without neighbours, such as those outside a mask, are not part of the maze
+        let all_cells: Vec<(usize, usize)> = (0..topology.rows())
+            .flat_map(|row| (0..topology.columns(row)).map(move |column| (row, column)))
+            .filter(|&(row, column)| !topology.neighbours(row, column).is_empty())
+            .collect();
+
+        let cell_degrees: Vec<Vec<usize>> = cells.iter()
+            .enumerate()
+            .map(|(row, columns)| (0..columns.len()).map(|column| degree(topology, cells, (row, column))).collect())
+            .collect();
+
+        let mut degrees = Vec::new();
+        for &(row, column) in &all_cells {
+            let degree = cell_degrees[row][column];
+            if degrees.len() <= degree {
+                degrees.resize(degree + 1, 0);
+            }
+            degrees[degree] += 1;
+        }
+
+        let dead_ends = degrees.get(1).copied().unwrap_or(0);
+
+        let (radial, along): (Vec<_>, Vec<_>) = all_cells.iter()
+            .flat_map(|&cell| topology.neighbours(cell.0, cell.1).into_iter().map(move |neighbour| (cell, neighbour)))
+            .filter(|&(cell, neighbour)| cell < neighbour && topology.linked(cells, cell, neighbour))
+            .partition(|&(cell, neighbour)| cell.0 != neighbour.0);
+
+        // Corridors are the connected runs of cells with two passages
+        let mut in_corridor: Vec<Vec<bool>> = cells.iter().map(|row| vec![false; row.len()]).collect();
+        let mut corridors = 0;
+        let mut corridor_cells = 0;
+        for &cell in &all_cells {
+            if cell_degrees[cell.0][cell.1] != 2 || in_corridor[cell.0][cell.1] {
+                continue;
+            }
+            corridors += 1;
+            in_corridor[cell.0][cell.1] = true;
+            let mut stack = vec![cell];
+            while let Some(current) = stack.pop() {
+                corridor_cells += 1;
+                for next in topology.neighbours(current.0, current.1) {
+                    if cell_degrees[next.0][next.1] == 2 && !in_corridor[next.0][next.1] && topology.linked(cells, current, next) {
+                        in_corridor[next.0][next.1] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        // Headings between consecutive cell centres along the solution
+        let headings: Vec<f64> = solution.windows(2)
+            .map(|pair| {
+                let a = topology.cell_centre(pair[0].0, pair[0].1, 0);
+                let b = topology.cell_centre(pair[1].0, pair[1].1, 0);
+                (b.1 - a.1).atan2(b.0 - a.0)
+            })
+            .collect();
+        let turns = headings.windows(2)
+            .filter(|pair| {
+                let change = (pair[1] - pair[0]).rem_euclid(std::f64::consts::TAU);
+                change.min(std::f64::consts::TAU - change) > TURN_ANGLE
+            })
+            .count();
+
+        let start = solution.first().copied().unwrap_or_else(|| topology.entrance());
+        let distances = distance_field(topology, cells, start);
+        let (deepest_cell, deepest_distance) = all_cells.iter()
+            .filter_map(|&(row, column)| distances[row][column].map(|distance| ((row, column), distance)))
+            .max_by_key(|&(_, distance)| distance)
+            .unwrap_or((start, 0));
+
+        Self {
+            cells: all_cells.len(),
+            solution_length: solution.len(),
+            dead_ends,
+            dead_end_ratio: dead_ends as f64 / all_cells.len().max(1) as f64,
+            degrees,
+            passages: radial.len() + along.len(),
+            radial_passages: radial.len(),
+            river_factor: corridor_cells as f64 / corridors.max(1) as f64,
+            turn_ratio: turns as f64 / headings.len().saturating_sub(1).max(1) as f64,
+            deepest_cell,
+            deepest_distance,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("{:<20} {:>10}", "Cells", self.cells);
+        println!("{:<20} {:>10}", "Solution length", self.solution_length);
+        println!("{:<20} {:>10}", "Dead ends", self.dead_ends);
+        println!("{:<20} {:>10.3}", "Dead end ratio", self.dead_end_ratio);
+        for (degree, count) in self.degrees.iter().enumerate().skip(3) {
+            println!("{:<20} {:>10}", format!("{}-way junctions", degree), count);
+        }
+        println!("{:<20} {:>10}", "Radial passages", format!("{}/{}", self.radial_passages, self.passages));
+        println!("{:<20} {:>10.3}", "River factor", self.river_factor);
+        println!("{:<20} {:>10.3}", "Turn ratio", self.turn_ratio);
+        println!("{:<20} {:>10}", "Deepest distance", self.deepest_distance);
+        println!("{:<20} {:>10}", "Deepest cell", format!("{:?}", self.deepest_cell));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{cell::Cell, config::MazeConfig, grid::Grid, topology::Topology};
+
+    use super::Metrics;
+
+    #[test]
+    fn metrics_of_a_serpentine() {
+        // Right along the top row, left along the middle one and right along the bottom one
+        let path = [(0, 0), (0, 1), (0, 2), (1, 2), (1, 1), (1, 0), (2, 0), (2, 1), (2, 2)];
+        let grid = Grid { cell_size: 10, width: 3, height: 3, solution: path.to_vec(), config: MazeConfig::default() };
+        let mut cells = vec![vec![Cell::new(); 3]; 3];
+        for pair in path.windows(2) {
+            grid.open_wall_between(&mut cells, pair[0], pair[1]);
+        }
+
+        let metrics = Metrics::new(&grid, &cells, &path);
+        assert_eq!((metrics.cells, metrics.solution_length, metrics.dead_ends), (9, 9, 2));
+        assert_eq!(metrics.degrees, vec![0, 2, 7]);
+        assert_eq!((metrics.passages, metrics.radial_passages), (8, 2));
+        assert_eq!(metrics.river_factor, 7.0);
+        assert_eq!((metrics.deepest_cell, metrics.deepest_distance), ((2, 2), 8));
+
+        // Each of the four corners turns by a right angle, more than `TURN_ANGLE`,
+        // while the other three of the seven changes of step go straight on
+        assert_eq!((metrics.turn_ratio * 7.0).round() as usize, 4);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashSet;
+
+use brickadia::save::{Brick, Color};
+use image::Rgb;
+
+use crate::metadata::assets::BrickAssets;
+use crate::utils::{brick::{bricks_from_shapes, paint, COLLECTIBLE_BRICK}, pixels::{gradient, Bitmap}, sfc32::SFC32, stroke::stroke_shapes};
+use super::{cell::Cell, solver::distance_field, topology::Topology};
+
+/// How many locked doors, each with its key, and collectibles to place in a carved maze
+#[derive(Debug, Clone, Default)]
+pub struct PuzzleConfig {
+    /// At most one door per passage along the solution, leaving the first cell free
+    pub keys: usize,
+    pub collectibles: usize,
+}
+
+/// Objects placed over the cells of a maze. The door and key of the same index share a colour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    /// Passages along the solution which are locked, in the order they are reached, as many as there are keys
+    pub doors: Vec<((usize, usize), (usize, usize))>,
+    /// The cell holding the key to each door
+    pub keys: Vec<(usize, usize)>,
+    pub collectibles: Vec<(usize, usize)>,
+}
+
+/// Locks passages along the solution of `topology` and hides the key to each door where it can be reached
+/// with only the doors before it unlocked. Keys are chosen among the farthest cells, by the distance field from
+/// the start, of the part of the maze the previous door opens up. Collectibles favour dead ends.
+/// Placement depends only on the cells and `seed`.
+pub fn place_puzzle<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], config: &PuzzleConfig, seed: [u32; 4]) -> Puzzle {
+
+    let mut s_random = SFC32::new(seed);
+    let path = topology.solution();
+    let (Some(&start), Some(&goal)) = (path.first(), path.last()) else {
+        return Puzzle { doors: Vec::new(), keys: Vec::new(), collectibles: Vec::new() };
+    };
+
+    // One door in each stretch of the solution, so that they are spread along it
+    let passages = path.len().saturating_sub(2);
+    let count = config.keys.min(passages);
+    let mut doors: Vec<((usize, usize), (usize, usize))> = (0..count)
+        .map(|door| {
+            let index = s_random.rand_between(1 + door * passages / count, 1 + (door + 1) * passages / count);
+            (path[index], path[index + 1])
+        })
+        .collect();
+
+    let mut locked = cells.to_vec();
+    for &(a, b) in &doors {
+        let ((row, column), wall) = topology.shared_wall(a, b);
+        *locked[row][column].wall_mut(wall) = true;
+    }
+
+    let mut reached: HashSet<(usize, usize)> = HashSet::new();
+    let mut keys = Vec::new();
+    for &(a, b) in &doors {
+        let distances = distance_field(topology, &locked, start);
+
+        let mut candidates: Vec<((usize, usize), usize)> = reachable(&distances)
+            .filter(|(cell, _)| *cell != start && !reached.contains(cell))
+            .collect();
+        candidates.sort_by_key(|&(cell, distance)| (std::cmp::Reverse(distance), cell));
+
+        // The farthest quarter of the cells opened up since the previous key
+        let farthest = candidates.len().div_ceil(4);
+        if farthest == 0 {
+            break;
+        }
+        keys.push(candidates[s_random.rand_between(0, farthest)].0);
+
+        reached.extend(reachable(&distances).map(|(cell, _)| cell));
+        topology.open_wall_between(&mut locked, a, b);
+    }
+
+    // A door whose key has nowhere to go is left open
+    doors.truncate(keys.len());
+
+    let distances = distance_field(topology, cells, start);
+    let (mut dead_ends, mut others): (Vec<_>, Vec<_>) = reachable(&distances)
+        .map(|(cell, _)| cell)
+        .filter(|cell| *cell != start && *cell != goal && !keys.contains(cell))
+        .partition(|&(row, column)| {
+            topology.neighbours(row, column).into_iter().filter(|&neighbour| topology.linked(cells, (row, column), neighbour)).count() == 1
+        });
+    s_random.shuffle(&mut dead_ends);
+    s_random.shuffle(&mut others);
+    let collectibles = dead_ends.into_iter().chain(others).take(config.collectibles).collect();
+
+    Puzzle { doors, keys, collectibles }
+}
+
+fn reachable(distances: &[Vec<Option<usize>>]) -> impl Iterator<Item = ((usize, usize), usize)> + '_ {
+    distances.iter().enumerate().flat_map(|(row, distances)| {
+        distances.iter().enumerate().filter_map(move |(column, distance)| distance.map(|distance| ((row, column), distance)))
+    })
+}
+
+/// The colour shared by a door and its key
+pub fn key_colour(index: usize, count: usize) -> Rgb<u8> {
+    gradient(index as f64 / count.saturating_sub(1).max(1) as f64)
+}
+
+/// Draws the walls with doors across their passages, keys in the colour of their door and collectibles in gold
+pub fn puzzle_map<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], puzzle: &Puzzle, wall_width: u32) -> Bitmap {
+
+    let (width, height) = topology.dimensions(wall_width);
+    let mut bitmap = Bitmap::with_dimensions(width, height);
+
+    for (index, &(row, column)) in puzzle.keys.iter().enumerate() {
+        topology.fill_cell(&mut bitmap, row, column, wall_width, key_colour(index, puzzle.doors.len()));
+    }
+    let Color { r, g, b, .. } = COLLECTIBLE_BRICK;
+    for &(row, column) in &puzzle.collectibles {
+        topology.fill_cell(&mut bitmap, row, column, wall_width, Rgb([r, g, b]));
+    }
+
+    topology.draw_walls(&mut bitmap, cells, wall_width);
+
+    for (index, &door) in puzzle.doors.iter().enumerate() {
+        let (begin, end) = door_line(topology, door, wall_width);
+        bitmap.line(begin, end, key_colour(index, puzzle.doors.len()), wall_width);
+    }
+
+    bitmap
+}
+
+/// A line across the passage between two cells, reaching the middle of the walls on either side
+fn door_line<T: Topology + ?Sized>(topology: &T, (a, b): ((usize, usize), (usize, usize)), wall_width: u32) -> ((f64, f64), (f64, f64)) {
+    let (ax, ay) = topology.cell_centre(a.0, a.1, wall_width);
+    let (bx, by) = topology.cell_centre(b.0, b.1, wall_width);
+    let (middle_x, middle_y) = ((ax + bx) / 2.0, (ay + by) / 2.0);
+    // Half the distance between the centres across, which is half a cell in a grid
+    let (across_x, across_y) = ((ay - by) / 2.0, (bx - ax) / 2.0);
+    ((middle_x - across_x, middle_y - across_y), (middle_x + across_x, middle_y + across_y))
+}
+
+/// Doors as walls of their colour across the passages, and keys and collectibles as markers
+/// as wide as a wall in the middle of their cells, all standing `wall_height` tall like the walls
+pub fn puzzle_bricks<T: Topology + ?Sized>(topology: &T, puzzle: &Puzzle, wall_width: u32, wall_height: u32) -> Vec<Brick> {
+
+    let mut bricks: Vec<Brick> = Vec::new();
+
+    let marker = |(row, column): (usize, usize)| {
+        let (x, y) = topology.cell_centre(row, column, wall_width);
+        let half = wall_width as f64 / 2.0;
+        vec![((x - half).round() as i32, (y - half).round() as i32), ((x + half).round() as i32, (y + half).round() as i32)]
+    };
+
+    for (index, (&door, &key)) in puzzle.doors.iter().zip(&puzzle.keys).enumerate() {
+        let (begin, end) = door_line(topology, door, wall_width);
+        let (mut rectangles, triangles) = stroke_shapes(begin, end, wall_width);
+        rectangles.push(marker(key));
+
+        let mut key_bricks = Vec::new();
+        bricks_from_shapes(&mut key_bricks, rectangles, wall_height, 0, None, BrickAssets::MicroBrick.index() as u32);
+        bricks_from_shapes(&mut key_bricks, triangles, wall_height, 0, Some(0), BrickAssets::MicroWedge.index() as u32);
+
+        let Rgb([r, g, b]) = key_colour(index, puzzle.doors.len());
+        paint(&mut key_bricks, &Color { r, g, b, a: 255 });
+        bricks.extend(key_bricks);
+    }
+
+    let mut collectibles = Vec::new();
+    bricks_from_shapes(&mut collectibles, puzzle.collectibles.iter().map(|&cell| marker(cell)).collect(), wall_height, 0, None, BrickAssets::MicroBrick.index() as u32);
+    paint(&mut collectibles, &COLLECTIBLE_BRICK);
+    bricks.extend(collectibles);
+
+    bricks
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{cell::Cell, config::MazeConfig, grid::Grid, maze::Maze, solver::distance_field, topology::Topology};
+
+    use super::{place_puzzle, puzzle_bricks, Puzzle, PuzzleConfig};
+
+    #[test]
+    fn keys_are_reached_before_their_doors() {
+        let config = PuzzleConfig { keys: 3, collectibles: 4 };
+        let mut grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: MazeConfig::default() };
+        let mut maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
+        let topologies: [&mut dyn Topology; 2] = [&mut grid, &mut maze];
+
+        for topology in topologies {
+            let cells = topology.get_cells([11, 13, 15, 2]);
+            let puzzle = place_puzzle(topology, &cells, &config, [11, 13, 15, 2]);
+            assert_eq!(puzzle, place_puzzle(topology, &cells, &config, [11, 13, 15, 2]));
+            assert_eq!((puzzle.doors.len(), puzzle.keys.len(), puzzle.collectibles.len()), (3, 3, 4));
+
+            let path = topology.solution();
+            let (start, goal) = (path[0], path[path.len() - 1]);
+            let on_path = |cell| path.iter().position(|&step| step == cell).unwrap();
+
+            let mut locked = cells.clone();
+            for &(a, b) in &puzzle.doors {
+                assert_eq!(on_path(a) + 1, on_path(b), "{a:?} {b:?}");
+                let ((row, column), wall) = topology.shared_wall(a, b);
+                *locked[row][column].wall_mut(wall) = true;
+            }
+
+            for (&(a, b), &(row, column)) in puzzle.doors.iter().zip(&puzzle.keys) {
+                let distances = distance_field(topology, &locked, start);
+                assert!(distances[row][column].is_some(), "The key in ({row}, {column}) is locked away");
+                // In a perfect maze the door cannot be bypassed
+                assert!(distances[goal.0][goal.1].is_none());
+                topology.open_wall_between(&mut locked, a, b);
+            }
+            assert!(distance_field(topology, &locked, start)[goal.0][goal.1].is_some());
+
+            assert!(!puzzle_bricks(topology, &puzzle, 3, 2).is_empty());
+        }
+    }
+
+    #[test]
+    fn a_maze_without_a_solution_has_an_empty_puzzle() {
+        let grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: MazeConfig::default() };
+        let cells = vec![vec![Cell::new(); 12]; 9];
+        let puzzle = place_puzzle(&grid, &cells, &PuzzleConfig { keys: 3, collectibles: 4 }, [11, 13, 15, 2]);
+        assert_eq!(puzzle, Puzzle { doors: Vec::new(), keys: Vec::new(), collectibles: Vec::new() });
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use crate::utils::sfc32::SFC32;
+use super::{algorithms::Sets, cell::Cell, topology::Topology};
+
+/// An open chamber placed before carving, spanning `rows` x `columns` cells from `(row, column)`.
+/// It is a rectangle of cells in a grid, and a sector of rings in a circular maze.
+#[derive(Debug, Clone)]
+pub struct Room {
+    pub row: usize,
+    pub column: usize,
+    pub rows: usize,
+    pub columns: usize,
+    /// The number of passages between the room and the rest of the maze, at least one.
+    /// A room splitting the maze in parts has a door into each part it touches, which may be more.
+    pub doors: usize,
+}
+
+/// Removes the walls inside every room and marks its cells as visited, so that carving goes around it.
+/// Returns the cells of each room, leaving out cells which were already visited.
+pub fn place_rooms<T: Topology + ?Sized>(topology: &T, cells: &mut [Vec<Cell>], rooms: &[Room]) -> Vec<Vec<(usize, usize)>> {
+
+    let mut placed: Vec<Vec<(usize, usize)>> = Vec::new();
+    let mut taken: HashSet<(usize, usize)> = HashSet::new();
+
+    for room in rooms {
+        let room_cells: Vec<(usize, usize)> = topology.room_cells(room)
+            .into_iter()
+            .filter(|&(row, column)| !cells[row][column].visited && !taken.contains(&(row, column)))
+            .collect();
+
+        let inside: HashSet<(usize, usize)> = room_cells.iter().copied().collect();
+        for &cell in &room_cells {
+            for neighbour in topology.neighbours(cell.0, cell.1) {
+                if inside.contains(&neighbour) {
+                    topology.open_wall_between(cells, cell, neighbour);
+                }
+            }
+            cells[cell.0][cell.1].visited = true;
+        }
+
+        taken.extend(room_cells.iter().copied());
+        placed.push(room_cells);
+    }
+
+    placed
+}
+
+/// Joins every room to the carved maze around it through its doors, chosen at random along its edge.
+/// Parts of the maze cut off from each other by the rooms are joined first, so that every cell can be reached.
+pub fn open_doors<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &mut [Vec<Cell>],
+    rooms: &[Room],
+    placed: &[Vec<(usize, usize)>],
+    s_random: &mut SFC32,
+) {
+    let in_rooms: HashSet<(usize, usize)> = placed.iter().flatten().copied().collect();
+
+    // The cells already joined by passages, each room and each part of the maze carved on its own
+    let mut sets = Sets::new(cells);
+    for row in 0..topology.rows() {
+        for column in 0..topology.columns(row) {
+            for neighbour in topology.neighbours(row, column) {
+                if topology.linked(cells, (row, column), neighbour) {
+                    sets.union((row, column), neighbour);
+                }
+            }
+        }
+    }
+
+    for (room, room_cells) in rooms.iter().zip(placed) {
+        let mut walls: Vec<((usize, usize), (usize, usize))> = room_cells.iter()
+            .flat_map(|&cell| topology.neighbours(cell.0, cell.1)
+                .into_iter()
+                .filter(|&neighbour| !in_rooms.contains(&neighbour) && cells[neighbour.0][neighbour.1].visited)
+                .map(move |neighbour| (cell, neighbour)))
+            .collect();
+
+        s_random.shuffle(&mut walls);
+
+        let (mut doors, others): (Vec<_>, Vec<_>) = walls.into_iter().partition(|&(cell, neighbour)| sets.union(cell, neighbour));
+        doors.extend(others.into_iter().take(room.doors.max(1).saturating_sub(doors.len())));
+
+        for (cell, neighbour) in doors {
+            topology.open_wall_between(cells, cell, neighbour);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::maze::{algorithms::Algorithm, config::MazeConfig, grid::Grid, maze::Maze, solver::distance_field, topology::Topology};
+
+    use super::Room;
+
+    #[test]
+    fn rooms_are_open_and_reachable() {
+        let rooms = vec![
+            Room { row: 6, column: 0, rows: 3, columns: 4, doors: 2 },
+            Room { row: 2, column: 5, rows: 3, columns: 3, doors: 1 },
+        ];
+        let config = MazeConfig { rooms: rooms.clone(), ..Default::default() };
+        let mut grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: config.clone() };
+        let mut maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config };
+        let topologies: [&mut dyn Topology; 2] = [&mut grid, &mut maze];
+
+        for topology in topologies {
+            let cells = topology.get_cells([11, 13, 15, 2]);
+
+            for room in &rooms {
+                let room_cells = topology.room_cells(room);
+                assert!(!room_cells.is_empty());
+                for &cell in &room_cells {
+                    for neighbour in topology.neighbours(cell.0, cell.1) {
+                        if room_cells.contains(&neighbour) {
+                            assert!(topology.linked(&cells, cell, neighbour), "{cell:?} is walled off from {neighbour:?}");
+                        }
+                    }
+                }
+            }
+
+            let distances = distance_field(topology, &cells, topology.solution()[0]);
+            for (row, row_distances) in distances.iter().enumerate() {
+                for (column, distance) in row_distances.iter().enumerate() {
+                    assert!(distance.is_some(), "({row}, {column}) cannot be reached");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rooms_across_the_maze_are_crossed() {
+        for algorithm in Algorithm::iter() {
+            // Across the whole width of the grid and the whole of a ring
+            let grid_config = MazeConfig { algorithm, rooms: vec![Room { row: 4, column: 0, rows: 1, columns: 12, doors: 1 }], ..Default::default() };
+            let maze_config = MazeConfig { algorithm, rooms: vec![Room { row: 4, column: 0, rows: 2, columns: 64, doors: 1 }], ..Default::default() };
+            let mut grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: grid_config };
+            let mut maze = Maze { ring_gap: 10, rings: 8, initial_divisions: 4, solution: Vec::new(), config: maze_config };
+            let topologies: [&mut dyn Topology; 2] = [&mut grid, &mut maze];
+
+            for topology in topologies {
+                let cells = topology.get_cells([11, 13, 15, 2]);
+                let distances = distance_field(topology, &cells, topology.solution()[0]);
+                for (row, row_distances) in distances.iter().enumerate() {
+                    for (column, distance) in row_distances.iter().enumerate() {
+                        assert!(distance.is_some(), "{algorithm}: ({row}, {column}) cannot be reached");
+                    }
+                }
+            }
+        }
+    }
+}
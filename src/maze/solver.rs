@@ -0,0 +1,200 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, VecDeque}};
+
+use strum_macros::{Display, EnumIter};
+
+use super::{cell::Cell, topology::Topology};
+
+#[derive(Debug, Clone, Copy, Default, EnumIter, Display, PartialEq)]
+pub enum Search {
+    /// Counts the number of cells along the path
+    #[default]
+    BreadthFirst,
+    /// Measures the length of the path with `Topology::edge_length`
+    Dijkstra,
+    /// Measures the length of the path with `Topology::edge_length`, guided towards the goal.
+    /// Stops as soon as the goal is reached, so only explored cells have a distance.
+    AStar,
+}
+
+pub struct Solution {
+    /// The cells from the start to the goal, empty if the goal cannot be reached
+    pub path: Vec<(usize, usize)>,
+    /// The distance from the start to every cell, `None` where a cell was not reached
+    pub distances: Vec<Vec<Option<f64>>>,
+}
+
+/// A cell waiting to be explored, ordered so that `BinaryHeap` pops the lowest estimate first
+#[derive(PartialEq)]
+struct Candidate {
+    estimate: f64,
+    cell: (usize, usize),
+}
+impl Eq for Candidate {}
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Finds the shortest path from `start` to `goal` through the open walls of `cells`
+pub fn solve<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &[Vec<Cell>],
+    start: (usize, usize),
+    goal: (usize, usize),
+    search: Search,
+) -> Solution {
+    let (distances, previous) = match search {
+        Search::BreadthFirst => breadth_first(topology, cells, start),
+        Search::Dijkstra => weighted(topology, cells, start, None),
+        Search::AStar => weighted(topology, cells, start, Some(goal)),
+    };
+
+    Solution { path: walk_back(&previous, start, goal), distances }
+}
+
+/// The number of steps from `start` to every reachable cell
+pub fn distance_field<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], start: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let (distances, _) = breadth_first(topology, cells, start);
+    distances.into_iter()
+        .map(|row| row.into_iter().map(|distance| distance.map(|steps| steps as usize)).collect())
+        .collect()
+}
+
+type Previous = Vec<Vec<Option<(usize, usize)>>>;
+
+fn breadth_first<T: Topology + ?Sized>(topology: &T, cells: &[Vec<Cell>], start: (usize, usize)) -> (Vec<Vec<Option<f64>>>, Previous) {
+
+    let mut distances: Vec<Vec<Option<f64>>> = cells.iter().map(|row| vec![None; row.len()]).collect();
+    let mut previous: Previous = cells.iter().map(|row| vec![None; row.len()]).collect();
+    distances[start.0][start.1] = Some(0.0);
+
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(cell) = queue.pop_front() {
+        let distance = distances[cell.0][cell.1].unwrap_or_default();
+        for (row, column) in topology.neighbours(cell.0, cell.1) {
+            if distances[row][column].is_none() && topology.linked(cells, cell, (row, column)) {
+                distances[row][column] = Some(distance + 1.0);
+                previous[row][column] = Some(cell);
+                queue.push_back((row, column));
+            }
+        }
+    }
+
+    (distances, previous)
+}
+
+/// Dijkstra's algorithm over the lengths of the steps between cells,
+/// which becomes A* when a goal is given to estimate the remaining distance
+fn weighted<T: Topology + ?Sized>(
+    topology: &T,
+    cells: &[Vec<Cell>],
+    start: (usize, usize),
+    goal: Option<(usize, usize)>,
+) -> (Vec<Vec<Option<f64>>>, Previous) {
+
+    let heuristic = |cell: (usize, usize)| goal.map_or(0.0, |goal| topology.distance_estimate(cell, goal));
+
+    let mut distances: Vec<Vec<Option<f64>>> = cells.iter().map(|row| vec![None; row.len()]).collect();
+    let mut previous: Previous = cells.iter().map(|row| vec![None; row.len()]).collect();
+    let mut settled: Vec<Vec<bool>> = cells.iter().map(|row| vec![false; row.len()]).collect();
+    distances[start.0][start.1] = Some(0.0);
+
+    let mut queue = BinaryHeap::from([Candidate { estimate: heuristic(start), cell: start }]);
+
+    while let Some(Candidate { cell, .. }) = queue.pop() {
+        if settled[cell.0][cell.1] {
+            continue;
+        }
+        settled[cell.0][cell.1] = true;
+
+        if Some(cell) == goal {
+            break;
+        }
+
+        let distance = distances[cell.0][cell.1].unwrap_or_default();
+        for (row, column) in topology.neighbours(cell.0, cell.1) {
+            if settled[row][column] || !topology.linked(cells, cell, (row, column)) {
+                continue;
+            }
+            let candidate = distance + topology.edge_length(cell, (row, column));
+            if distances[row][column].is_none_or(|current| candidate < current) {
+                distances[row][column] = Some(candidate);
+                previous[row][column] = Some(cell);
+                queue.push(Candidate { estimate: candidate + heuristic((row, column)), cell: (row, column) });
+            }
+        }
+    }
+
+    (distances, previous)
+}
+
+fn walk_back(previous: &Previous, start: (usize, usize), goal: (usize, usize)) -> Vec<(usize, usize)> {
+
+    if goal != start && previous[goal.0][goal.1].is_none() {
+        return Vec::new();
+    }
+
+    let mut path = vec![goal];
+    let mut cell = goal;
+    while cell != start {
+        cell = previous[cell.0][cell.1].unwrap();
+        path.push(cell);
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::MazeConfig, hex::HexGrid, surface::{Surface, SurfaceGrid}, topology::Topology, wrap::{Layout, Wrap, WrapGrid}};
+
+    use super::{solve, Search};
+
+    #[test]
+    fn searches_agree_on_braid_mazes() {
+        let config = MazeConfig { dead_end_removal: 0.5, loops: 20, ..Default::default() };
+        let mut hex = HexGrid { cell_size: 10, width: 14, height: 11, solution: Vec::new(), config };
+        let cells = hex.get_cells([11, 13, 15, 2]);
+
+        let (start, goal) = (hex.entrance(), hex.exit());
+
+        let breadth_first = solve(&hex, &cells, start, goal, Search::BreadthFirst);
+        assert_eq!(breadth_first.path.len() - 1, breadth_first.distances[goal.0][goal.1].unwrap() as usize);
+
+        let dijkstra = solve(&hex, &cells, start, goal, Search::Dijkstra);
+        let a_star = solve(&hex, &cells, start, goal, Search::AStar);
+        let (shortest, guided) = (dijkstra.distances[goal.0][goal.1].unwrap(), a_star.distances[goal.0][goal.1].unwrap());
+        assert!((shortest - guided).abs() < 1e-9, "A* found a longer path: {guided} > {shortest}");
+        assert!(dijkstra.distances.iter().flatten().all(Option::is_some), "Dijkstra left cells unreached");
+
+        for pair in a_star.path.windows(2) {
+            assert!(hex.linked(&cells, pair[0], pair[1]));
+        }
+    }
+
+    #[test]
+    fn searches_agree_across_seams() {
+        let config = MazeConfig { dead_end_removal: 0.5, loops: 20, ..Default::default() };
+        let mut torus = WrapGrid { cell_size: 10, width: 12, height: 9, wrap: Wrap::Torus, layout: Layout::Flat, solution: Vec::new(), config: config.clone() };
+        let mut cube = SurfaceGrid { cell_size: 10, size: 5, surface: Surface::Cube, solution: Vec::new(), config };
+        let topologies: [&mut dyn Topology; 2] = [&mut torus, &mut cube];
+
+        for topology in topologies {
+            let cells = topology.get_cells([11, 13, 15, 2]);
+            let (start, goal) = (topology.entrance(), topology.exit());
+
+            let breadth_first = solve(topology, &cells, start, goal, Search::BreadthFirst);
+            let a_star = solve(topology, &cells, start, goal, Search::AStar);
+            assert!(!breadth_first.path.is_empty());
+            assert_eq!(a_star.path.len(), breadth_first.path.len());
+            assert_eq!(solve(topology, &cells, start, goal, Search::Dijkstra).path.len(), breadth_first.path.len());
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashSet;
+
+use brickadia::save::{Brick, Direction};
+
+use crate::metadata::assets::BrickAssets;
+use crate::utils::{brick::{oriented_bricks, paint, FLOOR_BRICK}, pixels::{Bitmap, RED}, sfc32::SFC32};
+use super::{algorithms::carve, cell::{Cell, Wall}, config::MazeConfig, rooms::{open_doors, place_rooms}, topology::{Topology, Walls}};
+
+/// The solid a `SurfaceGrid` is built on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Surface {
+    Cube,
+    /// The cube blown up into a sphere, whose walls and shell are built from voxels
+    Sphere,
+}
+
+/// The faces of the cube as `(normal axis, sign, horizontal axis, vertical axis)`
+const FACES: [(usize, i32, usize, usize); 6] = [
+    (0, 1, 1, 2),
+    (0, -1, 1, 2),
+    (1, 1, 0, 2),
+    (1, -1, 0, 2),
+    (2, 1, 0, 1),
+    (2, -1, 0, 1),
+];
+
+/// The sides of a cell as `(vertical, sign, wall)`: up, right, left and down across its face
+const SIDES: [(bool, i32, Wall); 4] = [
+    (true, -1, Wall::Inner),
+    (false, 1, Wall::Right),
+    (false, -1, Wall::InnerRight),
+    (true, 1, Wall::Outer),
+];
+
+/// A maze covering the six faces of a cube, each `size` x `size` cells, where passages run over the
+/// edges from one face to the next. Row `row` lies on face `row / size`. Inside a face, walls are owned
+/// as in `Grid`, and each wall on an edge of the cube is owned by the first of its two cells,
+/// through the wall on that side: `inner_wall` above, `right_wall`, `inner_right_wall` to the left and `outer_wall` below.
+/// The surface has no boundary, so nothing is opened: the solution joins opposite faces.
+pub struct SurfaceGrid {
+    pub cell_size: u32,
+    pub size: usize,
+    pub surface: Surface,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl SurfaceGrid {
+
+    /// The centre of a cell, in half cells from the centre of the cube
+    fn lattice(&self, row: usize, column: usize) -> [i32; 3] {
+        let n = self.size as i32;
+        let (normal, sign, horizontal, vertical) = FACES[row / self.size];
+        let mut point = [0; 3];
+        point[normal] = sign * n;
+        point[horizontal] = 2 * column as i32 + 1 - n;
+        point[vertical] = 2 * (row % self.size) as i32 + 1 - n;
+        point
+    }
+
+    fn cell_at(&self, point: [i32; 3]) -> (usize, usize) {
+        let n = self.size as i32;
+        let face = FACES.iter().position(|&(normal, sign, _, _)| point[normal] == sign * n).expect("Point off the cube");
+        let (_, _, horizontal, vertical) = FACES[face];
+        (face * self.size + ((point[vertical] + n - 1) / 2) as usize, ((point[horizontal] + n - 1) / 2) as usize)
+    }
+
+    /// The axis a side of a cell runs across
+    fn side_axis(&self, row: usize, side: usize) -> usize {
+        let (_, _, horizontal, vertical) = FACES[row / self.size];
+        if SIDES[side].0 { vertical } else { horizontal }
+    }
+
+    /// The cell beyond a side, folding over the edge of the cube onto the next face
+    fn step(&self, row: usize, column: usize, side: usize) -> (usize, usize) {
+        let n = self.size as i32;
+        let (normal, sign, _, _) = FACES[row / self.size];
+        let (_, direction, _) = SIDES[side];
+        let axis = self.side_axis(row, side);
+
+        let mut point = self.lattice(row, column);
+        point[axis] += 2 * direction;
+        if point[axis].abs() > n {
+            point[axis] = direction * n;
+            point[normal] = sign * (n - 1);
+        }
+        self.cell_at(point)
+    }
+
+    /// The side of `a` which faces `b`
+    fn side_towards(&self, a: (usize, usize), b: (usize, usize)) -> usize {
+        (0..SIDES.len()).find(|&side| self.step(a.0, a.1, side) == b).expect("Cells are not neighbours")
+    }
+
+    /// Whether the wall on a side of a cell is standing, wherever it is owned
+    fn standing(&self, cells: &[Vec<Cell>], row: usize, column: usize, side: usize) -> bool {
+        let ((owner_row, owner_column), wall) = self.shared_wall((row, column), self.step(row, column, side));
+        cells[owner_row][owner_column].wall(wall)
+    }
+
+    /// The ends of the wall on a side of a cell, in pixels from the centre of the cube
+    fn wall_segment(&self, row: usize, column: usize, side: usize) -> ([f64; 3], [f64; 3]) {
+        let (_, _, horizontal, vertical) = FACES[row / self.size];
+        let axis = self.side_axis(row, side);
+        let along = if axis == horizontal { vertical } else { horizontal };
+        let half_cell = self.cell_size as f64 / 2.0;
+
+        let centre = self.lattice(row, column);
+        let mut begin: [f64; 3] = std::array::from_fn(|k| centre[k] as f64 * half_cell);
+        begin[axis] += SIDES[side].1 as f64 * half_cell;
+        let mut end = begin;
+        begin[along] -= half_cell;
+        end[along] += half_cell;
+        (begin, end)
+    }
+
+    /// Every standing wall once, with the cell it was found from
+    fn standing_walls(&self, cells: &[Vec<Cell>]) -> Vec<((usize, usize), usize)> {
+        (0..self.rows())
+            .flat_map(|row| (0..self.size).map(move |column| (row, column)))
+            .flat_map(|cell| (0..SIDES.len()).map(move |side| (cell, side)))
+            .filter(|&((row, column), side)| {
+                let (owner, wall) = self.shared_wall((row, column), self.step(row, column, side));
+                owner == (row, column) && wall == SIDES[side].2 && cells[row][column].wall(wall)
+            })
+            .collect()
+    }
+
+    /// The walls of the maze standing out of the surface by `wall_height` pixels, over a shell of
+    /// `FLOOR_BRICK` as thick as a wall. Bricks point away from the face they stand on.
+    pub fn surface_bricks(&self, cells: &[Vec<Cell>], wall_width: u32, wall_height: u32) -> Vec<Brick> {
+        match self.surface {
+            Surface::Cube => self.cube_bricks(cells, wall_width, wall_height),
+            Surface::Sphere => self.sphere_bricks(cells, wall_width, wall_height),
+        }
+    }
+
+    fn cube_bricks(&self, cells: &[Vec<Cell>], wall_width: u32, wall_height: u32) -> Vec<Brick> {
+        let half = (self.cell_size * self.size as u32) as f64 / 2.0;
+        let (mid_wall, height) = (wall_width as f64 / 2.0, wall_height as f64);
+        let asset = BrickAssets::MicroBrick.index() as u32;
+
+        let mut shell = Vec::new();
+        for &(normal, sign, _, _) in &FACES {
+            let (min, max): ([f64; 3], [f64; 3]) = (
+                std::array::from_fn(|k| if k == normal { if sign > 0 { half - wall_width as f64 } else { -half } } else { -half }),
+                std::array::from_fn(|k| if k == normal { if sign > 0 { half } else { wall_width as f64 - half } } else { half }),
+            );
+            oriented_bricks(&mut shell, min.map(|x| x.round() as i32), max.map(|x| x.round() as i32), direction(normal, sign), asset);
+        }
+        paint(&mut shell, &FLOOR_BRICK);
+
+        let mut bricks = Vec::new();
+        for ((row, column), side) in self.standing_walls(cells) {
+            let (begin, end) = self.wall_segment(row, column, side);
+
+            // Across the wall and along it the box is as wide as the wall, and on the cube's faces it stands out of them
+            let span = |k: usize| -> (f64, f64) {
+                if begin[k] != end[k] {
+                    (begin[k].min(end[k]) - mid_wall, begin[k].max(end[k]) + mid_wall)
+                } else if begin[k] == half {
+                    (half, half + height)
+                } else if begin[k] == -half {
+                    (-half - height, -half)
+                } else {
+                    (begin[k] - mid_wall, begin[k] + mid_wall)
+                }
+            };
+            let (min, max): ([f64; 3], [f64; 3]) = (std::array::from_fn(|k| span(k).0), std::array::from_fn(|k| span(k).1));
+
+            let (normal, sign, _, _) = FACES[row / self.size];
+            oriented_bricks(&mut bricks, min.map(|x| x.round() as i32), max.map(|x| x.round() as i32), direction(normal, sign), asset);
+        }
+
+        shell.extend(bricks);
+        shell
+    }
+
+    fn sphere_bricks(&self, cells: &[Vec<Cell>], wall_width: u32, wall_height: u32) -> Vec<Brick> {
+        let half = (self.cell_size * self.size as u32) as f64 / 2.0;
+        // Four faces around the equator, as long around as they are on the cube
+        let radius = 4.0 * half / std::f64::consts::PI;
+        let (mid_wall, thickness) = (wall_width as f64 / 2.0, wall_width as f64);
+
+        let mut shell: HashSet<[i32; 3]> = HashSet::new();
+        let extent = (radius + 1.0).ceil() as i32;
+        for x in -extent..extent {
+            for y in -extent..extent {
+                for z in -extent..extent {
+                    let distance = ((x as f64 + 0.5).powi(2) + (y as f64 + 0.5).powi(2) + (z as f64 + 0.5).powi(2)).sqrt();
+                    if (radius - thickness..radius).contains(&distance) {
+                        shell.insert([x, y, z]);
+                    }
+                }
+            }
+        }
+
+        // The walls are projected from the cube onto the sphere, and stamped in voxels between its surface and their top
+        let mut walls: HashSet<[i32; 3]> = HashSet::new();
+        for ((row, column), side) in self.standing_walls(cells) {
+            let (begin, end) = self.wall_segment(row, column, side);
+            let samples = (self.cell_size as f64 * 2.0 * radius / half).ceil() as usize;
+
+            for sample in 0..=samples {
+                let t = sample as f64 / samples as f64;
+                let point: [f64; 3] = std::array::from_fn(|k| begin[k] + (end[k] - begin[k]) * t);
+                let length = point.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+                for layer in 0..(2 * wall_height).max(1) {
+                    let elevation = radius + layer as f64 / 2.0;
+                    let centre = point.map(|x| x / length * elevation);
+                    let (low, high) = (centre.map(|x| (x - mid_wall).round() as i32), centre.map(|x| (x + mid_wall).round() as i32));
+                    for x in low[0]..high[0].max(low[0] + 1) {
+                        for y in low[1]..high[1].max(low[1] + 1) {
+                            for z in low[2]..high[2].max(low[2] + 1) {
+                                walls.insert([x, y, z]);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut bricks = voxel_bricks(shell);
+        paint(&mut bricks, &FLOOR_BRICK);
+        bricks.extend(voxel_bricks(walls));
+        bricks
+    }
+}
+
+/// The direction pointing out of a face
+fn direction(normal: usize, sign: i32) -> Direction {
+    match (normal, sign > 0) {
+        (0, true) => Direction::XPositive,
+        (0, false) => Direction::XNegative,
+        (1, true) => Direction::YPositive,
+        (1, false) => Direction::YNegative,
+        (_, true) => Direction::ZPositive,
+        (_, false) => Direction::ZNegative,
+    }
+}
+
+/// Joins voxels into runs along x, each pointing along the axis its position leans towards the most
+fn voxel_bricks(voxels: HashSet<[i32; 3]>) -> Vec<Brick> {
+    let facing = |voxel: [i32; 3]| {
+        let centre = voxel.map(|x| x as f64 + 0.5);
+        let normal = (0..3).max_by(|&a, &b| centre[a].abs().total_cmp(&centre[b].abs())).unwrap();
+        direction(normal, if centre[normal] > 0.0 { 1 } else { -1 })
+    };
+
+    let mut sorted: Vec<[i32; 3]> = voxels.into_iter().collect();
+    sorted.sort_by_key(|&[x, y, z]| (z, y, x));
+
+    let mut bricks = Vec::new();
+    let mut index = 0;
+    while index < sorted.len() {
+        let first = sorted[index];
+        let mut last = first;
+        while index + 1 < sorted.len() {
+            let next = sorted[index + 1];
+            if next != [last[0] + 1, last[1], last[2]] || facing(next) != facing(first) {
+                break;
+            }
+            last = next;
+            index += 1;
+        }
+        oriented_bricks(&mut bricks, first, [last[0] + 1, last[1] + 1, last[2] + 1], facing(first), BrickAssets::MicroBrick.index() as u32);
+        index += 1;
+    }
+
+    bricks
+}
+
+impl Topology for SurfaceGrid {
+
+    /// Carves a maze from the entrance without opening any wall, since the surface has no edge
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+
+        let mut cells = self.new_cells();
+        let mut s_random = SFC32::new(seed);
+
+        let rooms = place_rooms(self, &mut cells, &self.config.rooms);
+
+        let entrance = self.entrance();
+        let start = self.carving_start(&cells, entrance);
+        carve(self, &mut cells, start, self.config.algorithm, &mut s_random);
+        open_doors(self, &mut cells, &self.config.rooms, &rooms, &mut s_random);
+        self.braid(&mut cells, &mut s_random);
+
+        let goal = self.goal(&cells, &[entrance], &mut s_random);
+        self.solution = self.solve(&cells, entrance, goal).path;
+
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        6 * self.size
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.size
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        (0..SIDES.len()).map(|side| self.step(row, column, side)).collect()
+    }
+
+    fn shared_wall(&self, a: (usize, usize), b: (usize, usize)) -> ((usize, usize), Wall) {
+        let (side_a, side_b) = (self.side_towards(a, b), self.side_towards(b, a));
+        if a.0 / self.size == b.0 / self.size {
+            // On the same face, the cell below owns its top wall and the cell on the left its right wall
+            match SIDES[side_a].2 {
+                Wall::Inner | Wall::Right => (a, SIDES[side_a].2),
+                _ => (b, SIDES[side_b].2),
+            }
+        } else if a < b {
+            (a, SIDES[side_a].2)
+        } else {
+            (b, SIDES[side_b].2)
+        }
+    }
+
+    /// The middle of the first face
+    fn entrance(&self) -> (usize, usize) {
+        (self.size / 2, self.size / 2)
+    }
+
+    /// The middle of the face opposite the entrance
+    fn exit(&self) -> (usize, usize) {
+        (self.size + self.size / 2, self.size / 2)
+    }
+
+    fn boundary(&self) -> Vec<(usize, usize)> {
+        Vec::new()
+    }
+
+    /// Every step is one cell across, within a face or over an edge
+    fn edge_length(&self, _a: (usize, usize), _b: (usize, usize)) -> f64 {
+        self.cell_size as f64
+    }
+
+    /// Half the lattice distance between the cells, since no step moves more than two half cells around the cube
+    fn distance_estimate(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> f64 {
+        let (a, b) = (self.lattice(row_a, column_a), self.lattice(row_b, column_b));
+        let half_cells: u32 = (0..3).map(|axis| a[axis].abs_diff(b[axis])).sum();
+        (half_cells * self.cell_size) as f64 / 2.0
+    }
+
+    /// The six faces side by side, three to a row, with the walls on the edges of the cube drawn on both faces
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        let mut lines = Vec::new();
+
+        for row in 0..self.rows() {
+            for column in 0..self.size {
+                let (centre_x, centre_y) = self.cell_centre(row, column, wall_width);
+                let (left, top) = (centre_x - size / 2.0, centre_y - size / 2.0);
+                let (right, bottom) = (left + size, top + size);
+
+                for side in 0..SIDES.len() {
+                    let neighbour = self.step(row, column, side);
+                    let (owner, _) = self.shared_wall((row, column), neighbour);
+                    let across_edge = neighbour.0 / self.size != row / self.size;
+                    if !self.standing(cells, row, column, side) || (owner != (row, column) && !across_edge) {
+                        continue;
+                    }
+                    lines.push(match side {
+                        0 => ((left - mid_wall, top), (right + mid_wall, top)),
+                        1 => ((right, top - mid_wall), (right, bottom + mid_wall)),
+                        2 => ((left, top - mid_wall), (left, bottom + mid_wall)),
+                        _ => ((left - mid_wall, bottom), (right + mid_wall, bottom)),
+                    });
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let size = self.cell_size as f64;
+        let face = row / self.size;
+        let face_size = size * self.size as f64;
+        (
+            wall_width as f64 + (face % 3) as f64 * face_size + (column as f64 + 0.5) * size,
+            wall_width as f64 + (face / 3) as f64 * face_size + ((row % self.size) as f64 + 0.5) * size,
+        )
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let face_size = self.cell_size * self.size as u32;
+        (3 * face_size + 2 * wall_width, 2 * face_size + 2 * wall_width)
+    }
+
+    /// Draws a path like `Topology::draw_path`, lifting the pen where it goes over an edge onto another face
+    fn draw_path(&self, bitmap: &mut Bitmap, path: &[(usize, usize)], wall_width: u32) {
+        for step in path.windows(2) {
+            let ((row_a, column_a), (row_b, column_b)) = (step[0], step[1]);
+            if row_a / self.size == row_b / self.size {
+                bitmap.line(self.cell_centre(row_a, column_a, wall_width), self.cell_centre(row_b, column_b, wall_width), RED, 1);
+            }
+        }
+    }
+
+    /// The flat faces are drawn as images, and the bricks are built on the surface
+    fn generate(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, _granularity: f64, solve: bool) -> Vec<Brick> {
+
+        let cells = self.get_cells(seed);
+        let solutions = self.solutions(&cells);
+
+        let bitmap = self.bitmap(&cells, wall_width);
+        if solve {
+            self.draw_solution(&bitmap, &solutions, wall_width);
+        }
+        self.svg(&cells, if solve { &solutions } else { &[] }, wall_width).save("maze");
+        bitmap.save("maze");
+
+        self.surface_bricks(&cells, wall_width, wall_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use brickadia::{save::Size, util::get_scale_axis};
+
+    use crate::maze::{config::MazeConfig, solver::distance_field, topology::Topology};
+
+    use super::{Surface, SurfaceGrid};
+
+    #[test]
+    fn surface_maze_folds_over_every_edge() {
+        let mut cube = SurfaceGrid { cell_size: 10, size: 5, surface: Surface::Cube, solution: Vec::new(), config: MazeConfig::default() };
+        let cells = cube.get_cells([11, 13, 15, 2]);
+
+        let mut passages = 0;
+        for row in 0..cube.rows() {
+            for column in 0..cube.columns(row) {
+                let neighbours = cube.neighbours(row, column);
+                assert_eq!(neighbours.len(), 4);
+                for neighbour in neighbours {
+                    assert!(cube.neighbours(neighbour.0, neighbour.1).contains(&(row, column)), "({row}, {column}) {neighbour:?}");
+                    passages += cube.linked(&cells, (row, column), neighbour) as usize;
+                }
+            }
+        }
+        // A spanning tree over all six faces, counted from both ends of each passage
+        assert_eq!(passages, 2 * (6 * 25 - 1));
+        let distances = distance_field(&cube, &cells, cube.entrance());
+        assert!(distances.iter().flatten().all(Option::is_some));
+
+        for surface in [Surface::Cube, Surface::Sphere] {
+            cube.surface = surface;
+            let bricks = cube.surface_bricks(&cells, 2, 3);
+            let mut directions: Vec<_> = bricks.iter().map(|brick| brick.direction).collect();
+            directions.sort();
+            directions.dedup();
+            assert_eq!(directions.len(), 6, "{surface:?}");
+
+            // Every brick stays within the walls standing out of the sphere, the larger of the two solids
+            let limit = 2 * (32 + 3 + 2);
+            for brick in &bricks {
+                let Size::Procedural(x, y, z) = brick.size else { panic!("{:?}", brick.size) };
+                let local = [x, y, z];
+                let position = [brick.position.0, brick.position.1, brick.position.2];
+                for axis in 0..3 {
+                    let extent = local[get_scale_axis(brick, axis as u8) as usize] as i32;
+                    assert!(position[axis].abs() + extent <= limit, "{surface:?} {brick:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,691 @@
+use std::collections::BTreeMap;
+
+use brickadia::save::{Brick, Color};
+use image::{Rgb, RgbImage};
+
+use crate::metadata::assets::BrickAssets;
+use crate::utils::{brick::{bricks_from_shapes, paint}, pixels::{gradient, Bitmap, Cap, BLACK, RED}, sfc32::SFC32, stroke::{arc_chords, stroke_shapes}, svg::Svg, walk::{brick_pixels, brick_pixels_at}};
+
+use super::{algorithms::carve, braid::{add_loops, remove_dead_ends}, cell::{Cell, Wall}, config::{Goal, MazeConfig}, metrics::Metrics, rooms::{open_doors, place_rooms, Room}, solver::{distance_field, solve, Solution}};
+
+/// The number of colours in a heat map
+pub const HEAT_LEVELS: usize = 16;
+
+/// The outline of a plate laid under or over a maze
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Plate {
+    /// The whole image the maze is drawn onto
+    BoundingBox,
+    /// Only the area covered by the maze, a disc for circular mazes
+    Footprint,
+}
+
+/// The arcs `(radius, angle_begin, angle_end)` and the line segments `(begin, end)` of the standing walls
+pub type Walls = (Vec<(f64, f64, f64)>, Vec<((f64, f64), (f64, f64))>);
+
+/// The layout of a maze: how many cells there are, which cells neighbour each other
+/// and where their walls are drawn. Cells are addressed as `(row, column)`,
+/// which for a circular maze is `(ring, division)`.
+pub trait Topology {
+
+    /// Carves the maze and records its solution
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>>;
+
+    fn solution(&self) -> &[(usize, usize)];
+
+    fn config(&self) -> &MazeConfig;
+
+    fn rows(&self) -> usize;
+
+    fn columns(&self, row: usize) -> usize;
+
+    /// Computes and returns `Vec<(row, column)>` of all adjacent cells
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)>;
+
+    /// The cell owning the wall between two adjacent cells, and which of its walls it is
+    fn shared_wall(&self, a: (usize, usize), b: (usize, usize)) -> ((usize, usize), Wall);
+
+    /// Computes the arcs `(radius, angle_begin, angle_end)` around `centre`
+    /// and the line segments of every standing wall
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls;
+
+    /// The cartesian coordinates of the middle of a cell
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64);
+
+    /// The `(width, height)` of the image the maze is drawn onto
+    fn dimensions(&self, wall_width: u32) -> (u32, u32);
+
+    /// The cell whose outer wall is opened to enter the maze
+    fn entrance(&self) -> (usize, usize) {
+        (self.rows() - 1, 0)
+    }
+
+    /// The cell whose inner wall is opened to leave the maze
+    fn exit(&self) -> (usize, usize) {
+        (0, self.columns(0) - 1)
+    }
+
+    /// The wall left open in `cells` to leave the maze, if any: the inner wall of the exit on the boundary
+    fn exit_wall(&self, cells: &[Vec<Cell>]) -> Option<((usize, usize), Wall)> {
+        let exit = self.exit();
+        (!self.boundary().is_empty() && !cells[exit.0][exit.1].inner_wall).then_some((exit, Wall::Inner))
+    }
+
+    /// The cells whose `outer_wall` lies on the outer boundary, in order along it
+    fn boundary(&self) -> Vec<(usize, usize)> {
+        let row = self.rows() - 1;
+        (0..self.columns(row)).map(|column| (row, column)).collect()
+    }
+
+    /// The cells opened to enter the maze, spread evenly along the boundary starting from `entrance`
+    fn entrances(&self) -> Vec<(usize, usize)> {
+        let boundary = self.boundary();
+        if boundary.is_empty() {
+            return vec![self.entrance()];
+        }
+        let count = self.config().entrances.clamp(1, boundary.len());
+        let first = boundary.iter().position(|&cell| cell == self.entrance()).unwrap_or(0);
+        (0..count).map(|index| boundary[(first + index * boundary.len() / count) % boundary.len()]).collect()
+    }
+
+    /// The cells covered by a room, a rectangle of rows and columns unless the topology shapes it otherwise
+    fn room_cells(&self, room: &Room) -> Vec<(usize, usize)> {
+        (room.row..(room.row + room.rows).min(self.rows()))
+            .flat_map(|row| (room.column..(room.column + room.columns).min(self.columns(row))).map(move |column| (row, column)))
+            .collect()
+    }
+
+    /// The cell carving starts from: the first entrance, or the first cell left to carve if a room covers it
+    fn carving_start(&self, cells: &[Vec<Cell>], entrance: (usize, usize)) -> (usize, usize) {
+        if !cells[entrance.0][entrance.1].visited {
+            return entrance;
+        }
+        (0..self.rows())
+            .flat_map(|row| (0..self.columns(row)).map(move |column| (row, column)))
+            .find(|&(row, column)| !cells[row][column].visited)
+            .unwrap_or(entrance)
+    }
+
+    /// Opens the way out of the carved `cells`, given the order they were carved in, and returns the cell it
+    /// leads out of when the solution ends there. The exit is only opened when the maze is left through it.
+    fn open_exit(&self, cells: &mut [Vec<Cell>], _order: &[(usize, usize)]) -> Option<(usize, usize)> {
+        if self.config().goal != Goal::Exit {
+            return None;
+        }
+        let exit = self.exit();
+        cells[exit.0][exit.1].inner_wall = false;
+        Some(exit)
+    }
+
+    /// Chooses the cell where the solution ends, once the maze is carved
+    fn goal(&self, cells: &[Vec<Cell>], entrances: &[(usize, usize)], s_random: &mut SFC32) -> (usize, usize) {
+
+        // Cells without neighbours, such as those outside a mask, are not part of the maze
+        let maze_cells: Vec<(usize, usize)> = (0..self.rows())
+            .flat_map(|row| (0..self.columns(row)).map(move |column| (row, column)))
+            .filter(|&(row, column)| !self.neighbours(row, column).is_empty())
+            .collect();
+
+        match self.config().goal {
+            Goal::Exit => self.exit(),
+            Goal::Centre => {
+                let (x, y) = self.centre(0);
+                maze_cells.into_iter()
+                    .min_by(|&a, &b| {
+                        let distance = |(row, column): (usize, usize)| {
+                            let (cx, cy) = self.cell_centre(row, column, 0);
+                            (cx - x).powi(2) + (cy - y).powi(2)
+                        };
+                        distance(a).total_cmp(&distance(b))
+                    })
+                    .unwrap_or_else(|| self.exit())
+            }
+            // A maze without cells ends where it starts
+            Goal::Random if maze_cells.is_empty() => entrances.first().copied().unwrap_or_else(|| self.entrance()),
+            Goal::Random => maze_cells[s_random.rand_between(0, maze_cells.len())],
+            Goal::Farthest => {
+                let fields: Vec<Vec<Vec<Option<usize>>>> = entrances.iter()
+                    .map(|&entrance| distance_field(self, cells, entrance))
+                    .collect();
+                maze_cells.into_iter()
+                    .filter_map(|(row, column)| fields.iter()
+                        .map(|field| field[row][column])
+                        .min()
+                        .flatten()
+                        .map(|distance| ((row, column), distance)))
+                    .max_by_key(|&(_, distance)| distance)
+                    .map_or_else(|| self.exit(), |(cell, _)| cell)
+            }
+        }
+    }
+
+    /// The path from every entrance to the goal at the end of the recorded solution
+    fn solutions(&self, cells: &[Vec<Cell>]) -> Vec<Vec<(usize, usize)>> {
+        let Some(&goal) = self.solution().last() else {
+            return Vec::new();
+        };
+        self.entrances()
+            .into_iter()
+            .map(|entrance| self.solve(cells, entrance, goal).path)
+            .collect()
+    }
+
+    fn centre(&self, wall_width: u32) -> (f64, f64) {
+        let (width, height) = self.dimensions(wall_width);
+        (width as f64 / 2.0, height as f64 / 2.0)
+    }
+
+    fn new_cells(&self) -> Vec<Vec<Cell>> {
+        (0..self.rows()).map(|row| vec![Cell::new(); self.columns(row)]).collect()
+    }
+
+    fn unvisited_neighbours(&self, cells: &[Vec<Cell>], row: usize, column: usize) -> Vec<(usize, usize)> {
+        self.neighbours(row, column)
+            .into_iter()
+            .filter(|&(row, column)| !cells[row][column].visited)
+            .collect()
+    }
+
+    /// Removes the wall separating two adjacent cells
+    fn open_wall_between(&self, cells: &mut [Vec<Cell>], a: (usize, usize), b: (usize, usize)) {
+        let ((row, column), wall) = self.shared_wall(a, b);
+        *cells[row][column].wall_mut(wall) = false;
+    }
+
+    /// Whether there is a passage between two adjacent cells
+    fn linked(&self, cells: &[Vec<Cell>], a: (usize, usize), b: (usize, usize)) -> bool {
+        let ((row, column), wall) = self.shared_wall(a, b);
+        !cells[row][column].wall(wall)
+    }
+
+    /// The length of a step between two adjacent cells, weighing the weighted searches.
+    /// The distance between their centres, for topologies whose layout has no seams.
+    fn edge_length(&self, a: (usize, usize), b: (usize, usize)) -> f64 {
+        let ((ax, ay), (bx, by)) = (self.cell_centre(a.0, a.1, 0), self.cell_centre(b.0, b.1, 0));
+        ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt()
+    }
+
+    /// A lower bound on the length of any path between two cells, which guides A*
+    fn distance_estimate(&self, a: (usize, usize), b: (usize, usize)) -> f64 {
+        let ((ax, ay), (bx, by)) = (self.cell_centre(a.0, a.1, 0), self.cell_centre(b.0, b.1, 0));
+        ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt()
+    }
+
+    /// Finds the shortest path between two cells with the search chosen in the config
+    fn solve(&self, cells: &[Vec<Cell>], start: (usize, usize), goal: (usize, usize)) -> Solution {
+        solve(self, cells, start, goal, self.config().search)
+    }
+
+    /// Measures the carved maze along its recorded solution
+    fn metrics(&self, cells: &[Vec<Cell>]) -> Metrics {
+        Metrics::new(self, cells, self.solution())
+    }
+
+    /// Carves a maze from its entrances, returning the cells and the path from the first entrance to the goal
+    fn carve(&self, seed: [u32; 4]) -> (Vec<Vec<Cell>>, Vec<(usize, usize)>) {
+
+        let mut cells: Vec<Vec<Cell>> = self.new_cells();
+
+        let mut s_random = SFC32::new(seed);
+
+        let rooms = place_rooms(self, &mut cells, &self.config().rooms);
+
+        let entrances = self.entrances();
+        for &(row, column) in &entrances {
+            cells[row][column].outer_wall = false;
+        }
+
+        let start = self.carving_start(&cells, entrances[0]);
+        let order = carve(self, &mut cells, start, self.config().algorithm, &mut s_random);
+        open_doors(self, &mut cells, &self.config().rooms, &rooms, &mut s_random);
+        self.braid(&mut cells, &mut s_random);
+
+        let goal = match self.open_exit(&mut cells, &order) {
+            Some(goal) => goal,
+            None => self.goal(&cells, &entrances, &mut s_random),
+        };
+        let solution = self.solve(&cells, entrances[0], goal).path;
+
+        (cells, solution)
+    }
+
+    /// Opens additional walls in a carved maze according to the config, so that there is more than one route
+    fn braid(&self, cells: &mut [Vec<Cell>], s_random: &mut SFC32) {
+        remove_dead_ends(self, cells, self.config().dead_end_removal, s_random);
+        add_loops(self, cells, self.config().loops, s_random);
+    }
+
+    /// Draws the standing walls of `cells` onto a new `Bitmap`
+    fn bitmap(&self, cells: &[Vec<Cell>], wall_width: u32) -> Bitmap {
+
+        let (width, height) = self.dimensions(wall_width);
+
+        let mut bitmap = Bitmap::with_dimensions(width, height);
+
+        self.draw_walls(&mut bitmap, cells, wall_width);
+
+        bitmap
+    }
+
+    fn draw_walls(&self, bitmap: &mut Bitmap, cells: &[Vec<Cell>], wall_width: u32) {
+
+        let centre = self.centre(wall_width);
+
+        let (arcs, lines) = self.arcs_and_walls(cells, wall_width);
+
+        for (radius, angle_begin, angle_end) in arcs {
+            bitmap.arc(centre, radius, angle_begin, angle_end, BLACK, wall_width, Cap::Round);
+        }
+
+        for (begin, end) in lines {
+            bitmap.line(begin, end, BLACK, wall_width);
+        }
+    }
+
+    /// Fills the area of a cell up to the middle of its walls. Cells are square unless overridden,
+    /// as wide as the distance to their nearest neighbour.
+    fn fill_cell(&self, bitmap: &mut Bitmap, row: usize, column: usize, wall_width: u32, rgb: Rgb<u8>) {
+        let (x, y) = self.cell_centre(row, column, wall_width);
+        let half = self.cell_radius(row, column, wall_width) + (wall_width / 2) as f64;
+        if half.is_finite() {
+            let corner = |offset: f64| ((x + offset).round() as i32, (y + offset).round() as i32);
+            bitmap.filled_rectangle(&[corner(-half), corner(half)], rgb);
+        }
+    }
+
+    /// The colour of every cell reachable from the first entrance, along `gradient` from near to far.
+    /// Distances are grouped into `HEAT_LEVELS` bands, so that cells of a band share one colour.
+    fn heat_colours(&self, cells: &[Vec<Cell>]) -> Vec<Vec<Option<Rgb<u8>>>> {
+
+        let distances = distance_field(self, cells, self.entrances()[0]);
+        let farthest = distances.iter().flatten().flatten().copied().max().unwrap_or(0).max(1);
+
+        distances.into_iter()
+            .map(|row| row.into_iter()
+                .map(|distance| distance.map(|distance| {
+                    let level = distance * (HEAT_LEVELS - 1) / farthest;
+                    gradient(level as f64 / (HEAT_LEVELS - 1) as f64)
+                }))
+                .collect())
+            .collect()
+    }
+
+    /// Colours every cell by its distance from the entrance, with the walls drawn over it
+    fn heat_map(&self, cells: &[Vec<Cell>], wall_width: u32) -> Bitmap {
+
+        let (width, height) = self.dimensions(wall_width);
+
+        let mut bitmap = Bitmap::with_dimensions(width, height);
+
+        for (row, colours) in self.heat_colours(cells).into_iter().enumerate() {
+            for (column, colour) in colours.into_iter().enumerate() {
+                if let Some(rgb) = colour {
+                    self.fill_cell(&mut bitmap, row, column, wall_width, rgb);
+                }
+            }
+        }
+
+        self.draw_walls(&mut bitmap, cells, wall_width);
+
+        bitmap
+    }
+
+    /// The colours of `heat_map` as floor bricks whose bottom sits at `elevation`, one layer per colour
+    fn heat_bricks(&self, cells: &[Vec<Cell>], wall_width: u32, thickness: u32, elevation: i32) -> Vec<Brick> {
+
+        let (width, height) = self.dimensions(wall_width);
+
+        let mut bands: BTreeMap<[u8; 3], Vec<(usize, usize)>> = BTreeMap::new();
+        for (row, colours) in self.heat_colours(cells).into_iter().enumerate() {
+            for (column, colour) in colours.into_iter().enumerate() {
+                if let Some(Rgb(rgb)) = colour {
+                    bands.entry(rgb).or_default().push((row, column));
+                }
+            }
+        }
+
+        let mut bricks: Vec<Brick> = Vec::new();
+
+        for ([r, g, b], band) in bands {
+            let mut bitmap = Bitmap::with_dimensions(width, height);
+            for (row, column) in band {
+                self.fill_cell(&mut bitmap, row, column, wall_width, BLACK);
+            }
+
+            let mut band_bricks = brick_pixels_at(&mut bitmap, thickness, elevation);
+            paint(&mut band_bricks, &Color { r, g, b, a: 255 });
+            bricks.extend(band_bricks);
+        }
+
+        bricks
+    }
+
+    /// Draws the standing walls of `cells` as exact arcs and lines, and the solutions as thin red polylines
+    fn svg(&self, cells: &[Vec<Cell>], solutions: &[Vec<(usize, usize)>], wall_width: u32) -> Svg {
+
+        let (width, height) = self.dimensions(wall_width);
+        let centre = self.centre(wall_width);
+
+        let (arcs, lines) = self.arcs_and_walls(cells, wall_width);
+
+        let mut svg = Svg::with_dimensions(width, height);
+
+        for (radius, angle_begin, angle_end) in arcs {
+            svg.arc(centre, radius, angle_begin, angle_end, BLACK, wall_width);
+        }
+
+        for (begin, end) in lines {
+            svg.line(begin, end, BLACK, wall_width);
+        }
+
+        for solution in solutions.iter().filter(|solution| !solution.is_empty()) {
+            let points: Vec<(f64, f64)> = solution.iter().map(|&(row, column)| self.cell_centre(row, column, wall_width)).collect();
+            svg.polyline(&points, RED, 1);
+        }
+
+        svg
+    }
+
+    /// Draws a path between cell centres as a thin red line
+    fn draw_path(&self, bitmap: &mut Bitmap, path: &[(usize, usize)], wall_width: u32) {
+
+        let mut prev: Option<(f64, f64)> = None;
+        let stroke_width = 1;
+
+        for &(row, column) in path {
+
+            let point = self.cell_centre(row, column, wall_width);
+
+            if let Some(prev) = prev {
+                bitmap.line(prev, point, RED, stroke_width);
+            }
+
+            prev = Some(point);
+        }
+    }
+
+    fn draw_solution(&self, bitmap: &Bitmap, solutions: &[Vec<(usize, usize)>], wall_width: u32) {
+
+        let mut bitmap = bitmap.clone();
+
+        if solutions.iter().all(Vec::is_empty) {
+            println!("ERROR: Empty solution!");
+            return;
+        }
+
+        for solution in solutions {
+            self.draw_path(&mut bitmap, solution, wall_width);
+        }
+
+        bitmap.save("solution");
+    }
+
+    /// The area covered by the maze in black, used for floors beneath it
+    fn footprint(&self, wall_width: u32) -> Bitmap {
+        let (width, height) = self.dimensions(wall_width);
+        Bitmap::from_image(RgbImage::from_pixel(width, height, BLACK))
+    }
+
+    /// A plate of `colour` bricks with the outline of `plate`, whose bottom sits at `elevation`.
+    /// Laid at `-2 * thickness` it is a floor under the walls, and at `2 * wall_height` a ceiling over them.
+    fn plate_bricks(&self, plate: Plate, wall_width: u32, thickness: u32, elevation: i32, colour: Color) -> Vec<Brick> {
+
+        let mut bitmap = match plate {
+            Plate::BoundingBox => {
+                let (width, height) = self.dimensions(wall_width);
+                Bitmap::from_image(RgbImage::from_pixel(width, height, BLACK))
+            }
+            Plate::Footprint => self.footprint(wall_width),
+        };
+
+        // The decomposition splits the plate into rectangles of at most `MAX_SIZE`
+        let mut bricks = brick_pixels_at(&mut bitmap, thickness, elevation);
+        paint(&mut bricks, &colour);
+
+        bricks
+    }
+
+    /// The radius of the largest circle centred in a cell which does not touch its walls
+    fn cell_radius(&self, row: usize, column: usize, wall_width: u32) -> f64 {
+        let (x, y) = self.cell_centre(row, column, wall_width);
+        let spacing = self.neighbours(row, column)
+            .into_iter()
+            .map(|(row, column)| {
+                let (nx, ny) = self.cell_centre(row, column, wall_width);
+                ((nx - x).powi(2) + (ny - y).powi(2)).sqrt()
+            })
+            .fold(f64::INFINITY, f64::min);
+        (spacing / 2.0 - (wall_width / 2) as f64).max(0.0)
+    }
+
+    /// Turns carved `cells` into bricks, saving the rasterized maze as `maze.png` and its outline as `maze.svg`.
+    /// A granularity closer to 1 leads to a more granular approximation.
+    fn export(
+        &self,
+        cells: &[Vec<Cell>],
+        solutions: &[Vec<(usize, usize)>],
+        wall_width: u32,
+        wall_height: u32,
+        granularity: f64,
+        solve: bool,
+    ) -> Vec<Brick> {
+
+        let mut bitmap = self.bitmap(cells, wall_width);
+
+        if solve {
+            self.draw_solution(&bitmap, solutions, wall_width);
+        }
+
+        self.svg(cells, if solve { solutions } else { &[] }, wall_width).save("maze");
+
+        let bricks: Vec<Brick> = brick_pixels(&mut bitmap, wall_height);
+
+        let factor = (1.0 / granularity).round().max(1.0) as u32;
+        bitmap.downscale(factor);
+
+        bitmap.save("maze");
+
+        bricks
+    }
+
+    /// Turns the standing walls of `cells` straight into bricks, without drawing them onto a `Bitmap` first.
+    /// Lines become stepped runs of bricks and wedges, and arcs are split into chords which become runs in turn.
+    fn analytic_bricks(&self, cells: &[Vec<Cell>], wall_width: u32, wall_height: u32) -> Vec<Brick> {
+
+        let centre = self.centre(wall_width);
+        let (mut arcs, mut lines) = self.arcs_and_walls(cells, wall_width);
+
+        // Arcs of neighbouring cells on the same circle are joined, so that they share long chords
+        arcs.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        let mut joined: Vec<(f64, f64, f64)> = Vec::new();
+        for (radius, angle_begin, angle_end) in arcs {
+            match joined.last_mut() {
+                Some(last) if last.0 == radius && (last.2 - angle_begin).abs() < 1e-9 => last.2 = angle_end,
+                _ => joined.push((radius, angle_begin, angle_end)),
+            }
+        }
+
+        for (radius, angle_begin, angle_end) in joined {
+            let chords = arc_chords(centre, radius, angle_begin, angle_end);
+            // Chords are lengthened to close the gap on the outside of each bend
+            let bend = (angle_end - angle_begin).abs() / chords.len() as f64;
+            let overlap = (wall_width as f64 / 2.0 * (bend / 2.0).tan()).ceil();
+            lines.extend(chords.into_iter().map(|(begin, end)| extended(begin, end, overlap)));
+        }
+
+        let mut rectangles = Vec::new();
+        let mut triangles = Vec::new();
+        for (begin, end) in lines {
+            let (line_rectangles, line_triangles) = stroke_shapes(begin, end, wall_width);
+            rectangles.extend(line_rectangles);
+            triangles.extend(line_triangles);
+        }
+
+        let mut bricks: Vec<Brick> = Vec::new();
+        bricks_from_shapes(&mut bricks, rectangles, wall_height, 0, None, BrickAssets::MicroBrick.index() as u32);
+        bricks_from_shapes(&mut bricks, triangles, wall_height, 0, Some(0), BrickAssets::MicroWedge.index() as u32);
+
+        bricks
+    }
+
+    /// A trail of `colour` plates along every solution, as wide as a wall, whose bottom sits at `elevation`.
+    /// Saved as a separate build, the hint can be loaded or cleared in game without touching the maze.
+    fn hint_bricks(&self, solutions: &[Vec<(usize, usize)>], wall_width: u32, thickness: u32, elevation: i32, colour: Color) -> Vec<Brick> {
+
+        let mut rectangles = Vec::new();
+        let mut triangles = Vec::new();
+
+        for solution in solutions {
+            let points: Vec<(f64, f64)> = solution.iter().map(|&(row, column)| self.cell_centre(row, column, wall_width)).collect();
+            for step in points.windows(2) {
+                // Lengthened by half the width so that the trail has no gaps where it turns
+                let (begin, end) = extended(step[0], step[1], wall_width as f64 / 2.0);
+                let (step_rectangles, step_triangles) = stroke_shapes(begin, end, wall_width);
+                rectangles.extend(step_rectangles);
+                triangles.extend(step_triangles);
+            }
+        }
+
+        let mut bricks: Vec<Brick> = Vec::new();
+        bricks_from_shapes(&mut bricks, rectangles, thickness, elevation, None, BrickAssets::MicroBrick.index() as u32);
+        bricks_from_shapes(&mut bricks, triangles, thickness, elevation, Some(0), BrickAssets::MicroWedge.index() as u32);
+
+        paint(&mut bricks, &colour);
+
+        bricks
+    }
+
+    /// Exports carved `cells` like `export`, but builds their bricks with `analytic_bricks`
+    fn export_analytic(&self, cells: &[Vec<Cell>], solutions: &[Vec<(usize, usize)>], wall_width: u32, wall_height: u32, solve: bool) -> Vec<Brick> {
+
+        if solve {
+            self.draw_solution(&self.bitmap(cells, wall_width), solutions, wall_width);
+        }
+
+        self.svg(cells, if solve { solutions } else { &[] }, wall_width).save("maze");
+
+        self.analytic_bricks(cells, wall_width, wall_height)
+    }
+
+    /// Carves a maze like `generate`, but builds its bricks with `analytic_bricks`
+    fn generate_analytic(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, solve: bool) -> Vec<Brick> {
+
+        let cells = self.get_cells(seed);
+
+        let solutions = self.solutions(&cells);
+
+        self.export_analytic(&cells, &solutions, wall_width, wall_height, solve)
+    }
+
+    /// Turns cells carved earlier, such as those of a loaded maze, into bricks along the recorded solution
+    fn export_carved(&self, cells: &[Vec<Cell>], wall_width: u32, wall_height: u32, granularity: f64, solve: bool) -> Vec<Brick> {
+        self.export(cells, &[self.solution().to_vec()], wall_width, wall_height, granularity, solve)
+    }
+
+    fn generate(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, granularity: f64, solve: bool) -> Vec<Brick> {
+
+        let cells = self.get_cells(seed);
+
+        let solutions = self.solutions(&cells);
+
+        self.export(&cells, &solutions, wall_width, wall_height, granularity, solve)
+    }
+}
+
+/// Lengthens a wall at both ends so that walls meeting at an angle leave no gap in the corner
+pub fn extended((x0, y0): (f64, f64), (x1, y1): (f64, f64), length: f64) -> ((f64, f64), (f64, f64)) {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let norm = (dx * dx + dy * dy).sqrt();
+    if norm == 0.0 {
+        return ((x0, y0), (x1, y1));
+    }
+    let (ux, uy) = (dx / norm * length, dy / norm * length);
+    ((x0 - ux, y0 - uy), (x1 + ux, y1 + uy))
+}
+
+#[cfg(test)]
+mod tests {
+    use brickadia::save::{BrickColor, Size};
+
+    use crate::maze::{config::MazeConfig, grid::Grid, maze::Maze};
+    use crate::utils::{brick::{FLOOR_BRICK, HINT_BRICK, MAX_SIZE}, pixels::gradient};
+
+    use super::{Plate, Topology, HEAT_LEVELS};
+
+    #[test]
+    fn hint_covers_the_solution() {
+        let mut grid = Grid { cell_size: 10, width: 12, height: 9, solution: Vec::new(), config: MazeConfig::default() };
+        grid.get_cells([11, 13, 15, 2]);
+        let solution = grid.solution().to_vec();
+
+        let hint = grid.hint_bricks(std::slice::from_ref(&solution), 3, 1, 4, HINT_BRICK);
+
+        for &(row, column) in &solution {
+            let (x, y) = grid.cell_centre(row, column, 3);
+            let covered = hint.iter().any(|brick| {
+                let Size::Procedural(width, length, _) = brick.size else { return false };
+                // Bricks are positioned by their centre in half pixels
+                (brick.position.0 as f64 / 2.0 - x).abs() <= width as f64 / 2.0
+                    && (brick.position.1 as f64 / 2.0 - y).abs() <= length as f64 / 2.0
+            });
+            assert!(covered, "({row}, {column}) has no hint");
+        }
+        assert!(hint.iter().all(|brick| brick.color == BrickColor::Unique(HINT_BRICK) && brick.position.2 == 5));
+    }
+
+    #[test]
+    fn plates_are_split_into_bricks_of_at_most_max_size() {
+        let maze = Maze { ring_gap: 10, rings: 60, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
+        let (width, height) = maze.dimensions(5);
+        assert!(width > MAX_SIZE as u32 * 2);
+
+        let area = |bricks: &[brickadia::save::Brick]| bricks.iter()
+            .map(|brick| match brick.size { Size::Procedural(width, length, _) => (width * length) as f64, _ => 0.0 })
+            .sum::<f64>();
+
+        for plate in [Plate::BoundingBox, Plate::Footprint] {
+            let bricks = maze.plate_bricks(plate, 5, 2, -4, FLOOR_BRICK);
+            for brick in &bricks {
+                let Size::Procedural(width, length, height) = brick.size else { panic!("{:?}", brick.size) };
+                assert!(width <= MAX_SIZE as u32 && length <= MAX_SIZE as u32 && height == 2);
+                assert_eq!(brick.position.2, -2);
+                assert_eq!(brick.color, BrickColor::Unique(FLOOR_BRICK));
+            }
+
+            // Wedges count for a whole rectangle, so the disc is only compared roughly
+            let expected = match plate {
+                Plate::BoundingBox => (width * height) as f64,
+                Plate::Footprint => std::f64::consts::PI * (maze.ring_gap * maze.rings as u32 + 2) as f64 * (maze.ring_gap * maze.rings as u32 + 2) as f64,
+            };
+            assert!((area(&bricks) - expected).abs() < expected * 0.02, "{plate:?} covers {} instead of {expected}", area(&bricks));
+        }
+    }
+
+    #[test]
+    fn heat_map_colours_cells_by_distance() {
+        let mut maze = Maze { ring_gap: 10, rings: 12, initial_divisions: 4, solution: Vec::new(), config: MazeConfig::default() };
+        let cells = maze.get_cells([11, 13, 15, 2]);
+
+        let colours = maze.heat_colours(&cells);
+        let heat_map = maze.heat_map(&cells, 3);
+
+        let (row, column) = maze.entrances()[0];
+        assert_eq!(colours[row][column], Some(gradient(0.0)));
+
+        for (ring, ring_colours) in colours.iter().enumerate() {
+            for (division, &colour) in ring_colours.iter().enumerate() {
+                let (x, y) = maze.cell_centre(ring, division, 3);
+                assert_eq!(Some(*heat_map.image.get_pixel(x.round() as u32, y.round() as u32)), colour, "({ring}, {division})");
+            }
+        }
+
+        let bricks = maze.heat_bricks(&cells, 3, 1, -2);
+        let mut bands: Vec<BrickColor> = bricks.iter().map(|brick| brick.color.clone()).collect();
+        bands.dedup();
+        assert!(bands.len() <= HEAT_LEVELS && bands.len() > HEAT_LEVELS / 2, "{} bands", bands.len());
+    }
+}
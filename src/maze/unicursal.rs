@@ -0,0 +1,138 @@
+use super::{cell::Cell, grid::Grid, topology::Topology};
+
+/// Doubles a perfect maze carved on `grid` into a unicursal labyrinth, a single path with no choices.
+/// Every cell is split into 2 x 2 cells of half the size. The walls of the maze are kept, and a new wall
+/// runs down the middle of every passage, so that the path goes up one side of each passage and back down
+/// the other. Dead ends become U-turns.
+///
+/// The returned grid's solution is the path from the first entrance, which visits every cell when the
+/// maze has a single opening, and otherwise leaves through the next opening along the walls.
+///
+/// Only the rectangular `Grid` can be doubled, which is why it is the only topology taken. The divisions of
+/// each ring of a circular `Maze` follow from its radius, so its cells cannot be split in two.
+/// Returns an error if `cell_size` is odd, since the halved cells would not line up with the original ones.
+pub fn unicursal(grid: &Grid, cells: &[Vec<Cell>]) -> Result<(Grid, Vec<Vec<Cell>>), String> {
+
+    if !grid.cell_size.is_multiple_of(2) {
+        return Err(format!("Cells of size {} cannot be split in half", grid.cell_size));
+    }
+
+    let (width, height) = (grid.width, grid.height);
+
+    let mut doubled: Vec<Vec<Cell>> = (0..2 * height)
+        .map(|_| (0..2 * width).map(|_| Cell { visited: true, ..Cell::new() }).collect())
+        .collect();
+
+    for row in 0..height {
+        for column in 0..width {
+            let cell = &cells[row][column];
+
+            // Openings to the outside count as passages, so that the path leaves the labyrinth through them
+            let up = !cell.inner_wall;
+            let down = if row + 1 < height { !cells[row + 1][column].inner_wall } else { !cell.outer_wall };
+            let left = column > 0 && !cells[row][column - 1].right_wall;
+            let right = !cell.right_wall && column + 1 < width;
+
+            let (top, left_column) = (2 * row, 2 * column);
+
+            // The walls of the maze around the cell
+            for offset in 0..2 {
+                doubled[top][left_column + offset].inner_wall = cell.inner_wall;
+                doubled[top + offset][left_column + 1].right_wall = cell.right_wall;
+                doubled[top + 1][left_column + offset].outer_wall = cell.outer_wall;
+            }
+
+            // The new walls dividing each passage, running from the middle of the cell towards it
+            doubled[top][left_column].right_wall = up;
+            doubled[top + 1][left_column].right_wall = down;
+            doubled[top + 1][left_column].inner_wall = left;
+            doubled[top + 1][left_column + 1].inner_wall = right;
+        }
+    }
+
+    let mut labyrinth = Grid {
+        cell_size: grid.cell_size / 2,
+        width: 2 * width,
+        height: 2 * height,
+        solution: Vec::new(),
+        config: grid.config.clone(),
+    };
+
+    let (row, column) = grid.entrances()[0];
+    let start = (2 * row + 1, 2 * column);
+    labyrinth.solution = follow(&labyrinth, &doubled, start);
+
+    Ok((labyrinth, doubled))
+}
+
+/// Walks the only way forward from `start` until the path ends
+fn follow(labyrinth: &Grid, cells: &[Vec<Cell>], start: (usize, usize)) -> Vec<(usize, usize)> {
+
+    let mut path = vec![start];
+    let mut previous: Option<(usize, usize)> = None;
+    let mut current = start;
+
+    while let Some(next) = labyrinth.neighbours(current.0, current.1)
+        .into_iter()
+        .find(|&neighbour| Some(neighbour) != previous && labyrinth.linked(cells, current, neighbour))
+    {
+        // A maze with loops doubles into several circuits, which are only followed once around
+        if next == start {
+            break;
+        }
+        path.push(next);
+        previous = Some(current);
+        current = next;
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::maze::{algorithms::Algorithm, config::{Goal, MazeConfig}, grid::Grid, topology::Topology};
+
+    use super::unicursal;
+
+    #[test]
+    fn labyrinth_is_a_single_path_through_every_cell() {
+        use strum::IntoEnumIterator;
+
+        for algorithm in Algorithm::iter() {
+            // Without an exit, the entrance is the only opening
+            let config = MazeConfig { algorithm, goal: Goal::Farthest, ..Default::default() };
+            let mut grid = Grid { cell_size: 10, width: 9, height: 7, solution: Vec::new(), config };
+            let cells = grid.get_cells([11, 13, 15, 2]);
+
+            let (labyrinth, doubled) = unicursal(&grid, &cells).unwrap();
+            let path = labyrinth.solution();
+
+            assert_eq!(path.len(), 4 * 9 * 7, "{algorithm}");
+            assert_eq!(path.iter().collect::<HashSet<_>>().len(), path.len(), "{algorithm}");
+            for step in path.windows(2) {
+                assert!(labyrinth.linked(&doubled, step[0], step[1]));
+            }
+
+            // No choices: every cell has one way in and one way out
+            for row in 0..labyrinth.rows() {
+                for column in 0..labyrinth.columns(row) {
+                    let links = labyrinth.neighbours(row, column)
+                        .into_iter()
+                        .filter(|&neighbour| labyrinth.linked(&doubled, (row, column), neighbour))
+                        .count();
+                    let openings = (row == labyrinth.rows() - 1 && !doubled[row][column].outer_wall) as usize;
+                    assert_eq!(links + openings, 2, "{algorithm} ({row}, {column})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn odd_cells_are_not_halved() {
+        let mut grid = Grid { cell_size: 9, width: 9, height: 7, solution: Vec::new(), config: MazeConfig::default() };
+        let cells = grid.get_cells([11, 13, 15, 2]);
+        assert!(unicursal(&grid, &cells).is_err());
+    }
+}
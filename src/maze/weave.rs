@@ -0,0 +1,352 @@
+use brickadia::save::Brick;
+
+use crate::utils::{pixels::{Bitmap, BLACK}, sfc32::SFC32, walk::brick_pixels_at};
+use super::{algorithms::{carve, Algorithm, Sets}, cell::{Cell, Wall}, config::{Goal, MazeConfig}, rooms::{open_doors, place_rooms}, topology::{Topology, Walls}};
+
+/// A wall as the line segment `(begin, end)`
+type Segment = ((f64, f64), (f64, f64));
+
+/// The direction of a passage across the grid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// An orthogonal maze whose passages may cross: at a crossing one passage runs over a bridge
+/// while the other tunnels straight beneath it, linking the cells on either side of the crossing.
+/// Walls are owned as in `Grid`, and the tunnel beneath a crossing opens the walls on both sides of it.
+/// Always carved with Kruskal's algorithm, which keeps the crossings placed before carving,
+/// so `config.algorithm` is ignored.
+pub struct WeaveGrid {
+    pub cell_size: u32,
+    pub width: usize,
+    pub height: usize,
+    /// The fraction of cells where a crossing is attempted
+    pub density: f64,
+    /// The height of the gap between the top of the walls and the bridge decks
+    pub clearance: u32,
+    pub deck_thickness: u32,
+    /// The direction of the tunnel beneath each crossing, filled in when carving
+    pub crossings: Vec<Vec<Option<Axis>>>,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl WeaveGrid {
+
+    /// The top left corner of a cell
+    fn corner(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let margin = wall_width as f64;
+        let size = self.cell_size as f64;
+        (margin + column as f64 * size, margin + row as f64 * size)
+    }
+
+    fn crossing(&self, row: usize, column: usize) -> Option<Axis> {
+        self.crossings.get(row).and_then(|columns| columns.get(column)).copied().flatten()
+    }
+
+    /// Places crossings at random interior cells, opening the bridge over each one and the tunnel beneath it.
+    /// Crossings are never adjacent, and are skipped where they would close a loop.
+    fn place_crossings(&mut self, cells: &mut [Vec<Cell>], s_random: &mut SFC32) {
+
+        self.crossings = vec![vec![None; self.width]; self.height];
+
+        let mut candidates: Vec<(usize, usize)> = (1..self.height.saturating_sub(1))
+            .flat_map(|row| (1..self.width.saturating_sub(1)).map(move |column| (row, column)))
+            .collect();
+        s_random.shuffle(&mut candidates);
+
+        let mut sets = Sets::new(cells);
+
+        for (row, column) in candidates {
+            if s_random.rand_f64() >= self.density {
+                continue;
+            }
+
+            // Crossings stay clear of each other and of rooms
+            let around = [(row - 1, column), (row + 1, column), (row, column - 1), (row, column + 1)];
+            if around.iter().chain([&(row, column)]).any(|&(row, column)| self.crossing(row, column).is_some() || cells[row][column].visited) {
+                continue;
+            }
+
+            let tunnel = if s_random.rand_f64() < 0.5 { Axis::Horizontal } else { Axis::Vertical };
+            let [above, below, left, right] = around;
+            let (bridge_ends, tunnel_ends) = match tunnel {
+                Axis::Horizontal => ((above, below), (left, right)),
+                Axis::Vertical => ((left, right), (above, below)),
+            };
+
+            // The bridge joins three sets into one, and the tunnel must then join two different sets
+            let bridge = [sets.find(bridge_ends.0), sets.find((row, column)), sets.find(bridge_ends.1)];
+            let (start, end) = (sets.find(tunnel_ends.0), sets.find(tunnel_ends.1));
+            if bridge[0] == bridge[1] || bridge[1] == bridge[2] || bridge[0] == bridge[2]
+                || start == end || (bridge.contains(&start) && bridge.contains(&end)) {
+                continue;
+            }
+
+            sets.union(bridge_ends.0, (row, column));
+            sets.union((row, column), bridge_ends.1);
+            sets.union(tunnel_ends.0, tunnel_ends.1);
+
+            self.crossings[row][column] = Some(tunnel);
+
+            self.open_wall_between(cells, bridge_ends.0, (row, column));
+            self.open_wall_between(cells, (row, column), bridge_ends.1);
+
+            // The tunnel passes through both walls of the crossing on its way
+            self.open_wall_between(cells, tunnel_ends.0, tunnel_ends.1);
+            match tunnel {
+                Axis::Horizontal => cells[row][column].right_wall = false,
+                Axis::Vertical => cells[row][column].inner_wall = false,
+            }
+        }
+    }
+
+    /// The walls of a crossing beside its tunnel and beside its bridge, as line segments
+    fn crossing_walls(&self, row: usize, column: usize, tunnel: Axis, wall_width: u32) -> ([Segment; 2], [Segment; 2]) {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        let (left, top) = self.corner(row, column, wall_width);
+        let (right, bottom) = (left + size, top + size);
+
+        let horizontal = [((left - mid_wall, top), (right + mid_wall, top)), ((left - mid_wall, bottom), (right + mid_wall, bottom))];
+        let vertical = [((left, top - mid_wall), (left, bottom + mid_wall)), ((right, top - mid_wall), (right, bottom + mid_wall))];
+
+        match tunnel {
+            Axis::Horizontal => (horizontal, vertical),
+            Axis::Vertical => (vertical, horizontal),
+        }
+    }
+
+    /// The bricks raised above the walls at every crossing: supports beside the tunnel,
+    /// the bridge deck at `wall_height` plus `clearance`, and railings along the bridge
+    pub fn bridges(&self, wall_width: u32, wall_height: u32) -> Vec<Brick> {
+
+        let (width, height) = self.dimensions(wall_width);
+        let mut supports = Bitmap::with_dimensions(width, height);
+        let mut decks = Bitmap::with_dimensions(width, height);
+        let mut railings = Bitmap::with_dimensions(width, height);
+
+        let mid_wall = (wall_width / 2) as i32;
+        let size = self.cell_size as i32;
+
+        for row in 0..self.height {
+            for column in 0..self.width {
+                let Some(tunnel) = self.crossing(row, column) else {
+                    continue;
+                };
+
+                let (tunnel_sides, bridge_sides) = self.crossing_walls(row, column, tunnel, wall_width);
+                for (begin, end) in tunnel_sides {
+                    supports.line(begin, end, BLACK, wall_width);
+                }
+                for (begin, end) in bridge_sides {
+                    railings.line(begin, end, BLACK, wall_width);
+                }
+
+                let (left, top) = self.corner(row, column, wall_width);
+                let (left, top) = (left as i32, top as i32);
+                decks.filled_rectangle(&[(left - mid_wall, top - mid_wall), (left + size + mid_wall + 1, top + size + mid_wall + 1)], BLACK);
+            }
+        }
+
+        let deck_elevation = 2 * (wall_height + self.clearance) as i32;
+
+        let mut bricks = Vec::new();
+        if self.clearance > 0 {
+            bricks.extend(brick_pixels_at(&mut supports, self.clearance, 2 * wall_height as i32));
+        }
+        bricks.extend(brick_pixels_at(&mut decks, self.deck_thickness, deck_elevation));
+        bricks.extend(brick_pixels_at(&mut railings, wall_height, deck_elevation + 2 * self.deck_thickness as i32));
+        bricks
+    }
+}
+
+impl Topology for WeaveGrid {
+
+    /// Places the crossings, then carves a maze entered through the bottom left cell around them
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+
+        let mut cells = self.new_cells();
+        let mut s_random = SFC32::new(seed);
+
+        let rooms = place_rooms(self, &mut cells, &self.config.rooms);
+        self.place_crossings(&mut cells, &mut s_random);
+
+        let entrances = self.entrances();
+        for &(row, column) in &entrances {
+            cells[row][column].outer_wall = false;
+        }
+        if self.config.goal == Goal::Exit {
+            let exit = self.exit();
+            cells[exit.0][exit.1].inner_wall = false;
+        }
+
+        // The other algorithms would carve through the passages opened by the crossings, closing loops
+        let start = self.carving_start(&cells, entrances[0]);
+        carve(self, &mut cells, start, Algorithm::Kruskal, &mut s_random);
+        open_doors(self, &mut cells, &self.config.rooms, &rooms, &mut s_random);
+        self.braid(&mut cells, &mut s_random);
+
+        let goal = self.goal(&cells, &entrances, &mut s_random);
+        self.solution = self.solve(&cells, entrances[0], goal).path;
+
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width
+    }
+
+    /// A crossing only neighbours the two cells at the ends of its bridge,
+    /// while the cells at the ends of its tunnel neighbour each other
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+
+        let (row, column) = (row as isize, column as isize);
+        let directions = match self.crossing(row as usize, column as usize) {
+            Some(Axis::Horizontal) => vec![(-1, 0), (1, 0)],
+            Some(Axis::Vertical) => vec![(0, 1), (0, -1)],
+            None => vec![(0, 1), (0, -1), (-1, 0), (1, 0)],
+        };
+
+        for (dr, dc) in directions {
+            let (next_row, next_column) = (row + dr, column + dc);
+            if next_row < 0 || next_column < 0 || next_row >= self.height as isize || next_column >= self.width as isize {
+                continue;
+            }
+
+            let tunnel = if dr == 0 { Axis::Horizontal } else { Axis::Vertical };
+            if self.crossing(next_row as usize, next_column as usize) == Some(tunnel) {
+                // Straight through the tunnel to the cell beyond
+                neighbours.push(((next_row + dr) as usize, (next_column + dc) as usize));
+            } else {
+                neighbours.push((next_row as usize, next_column as usize));
+            }
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if row_a == row_b {
+            // Same row: the leftmost cell owns its right wall, which is also the entrance of a tunnel
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else {
+            // Different rows: the lower cell owns its top wall, which is also the exit of a tunnel
+            ((row_a.max(row_b), column_a), Wall::Inner)
+        }
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            for (column, cell) in row_cells.iter().enumerate() {
+                let (left, top) = self.corner(row, column, wall_width);
+                let (right, bottom) = (left + size, top + size);
+
+                if cell.inner_wall {
+                    lines.push(((left - mid_wall, top), (right + mid_wall, top)));
+                }
+                if cell.right_wall {
+                    lines.push(((right, top - mid_wall), (right, bottom + mid_wall)));
+                }
+                if row == self.height - 1 && cell.outer_wall {
+                    lines.push(((left - mid_wall, bottom), (right + mid_wall, bottom)));
+                }
+                if column == 0 {
+                    lines.push(((left, top - mid_wall), (left, bottom + mid_wall)));
+                }
+
+                // On the ground, the sides of a tunnel close the ends of the bridge above it
+                if let Some(tunnel) = self.crossing(row, column) {
+                    let (tunnel_sides, _) = self.crossing_walls(row, column, tunnel, wall_width);
+                    lines.extend(tunnel_sides);
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let half = self.cell_size as f64 / 2.0;
+        let (left, top) = self.corner(row, column, wall_width);
+        (left + half, top + half)
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        let margin = 2 * wall_width;
+        (
+            self.cell_size * self.width as u32 + margin,
+            self.cell_size * self.height as u32 + margin,
+        )
+    }
+
+    /// The walls on the ground, followed by the bridges over every crossing
+    fn generate(&mut self, seed: [u32; 4], wall_width: u32, wall_height: u32, granularity: f64, solve: bool) -> Vec<Brick> {
+
+        let cells = self.get_cells(seed);
+        let solutions = self.solutions(&cells);
+
+        let mut bricks = self.export(&cells, &solutions, wall_width, wall_height, granularity, solve);
+        bricks.extend(self.bridges(wall_width, wall_height));
+
+        bricks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{config::MazeConfig, topology::Topology};
+
+    use super::WeaveGrid;
+
+    #[test]
+    fn weave_is_a_perfect_maze_through_its_tunnels() {
+        let mut weave = WeaveGrid {
+            cell_size: 10, width: 15, height: 12, density: 0.5, clearance: 1, deck_thickness: 1,
+            crossings: Vec::new(), solution: Vec::new(), config: MazeConfig::default(),
+        };
+        let cells = weave.get_cells([11, 13, 15, 2]);
+
+        let crossings = weave.crossings.iter().flatten().filter(|crossing| crossing.is_some()).count();
+        assert!(crossings > 0, "no crossings were placed");
+
+        let mut count = 0;
+        let mut passages = 0;
+        for row in 0..weave.rows() {
+            for column in 0..weave.columns(row) {
+                assert!(cells[row][column].visited, "({row}, {column}) was never visited");
+                count += 1;
+                passages += weave.neighbours(row, column)
+                    .into_iter()
+                    .filter(|&neighbour| (row, column) < neighbour && weave.linked(&cells, (row, column), neighbour))
+                    .count();
+            }
+        }
+        assert_eq!(passages, count - 1, "the maze is not a spanning tree");
+        assert_eq!(weave.solution.last(), Some(&weave.exit()));
+    }
+}
@@ -0,0 +1,347 @@
+use std::f64::consts::TAU;
+
+use image::Rgb;
+
+use crate::utils::{math::TupleMath, pixels::{Bitmap, BLACK, RED}};
+use super::{cell::{Cell, Wall}, config::MazeConfig, topology::{Topology, Walls}};
+
+/// How the edges of a `WrapGrid` are joined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wrap {
+    /// The left and right edges are joined
+    Cylinder,
+    /// The left and right edges are joined, and so are the top and bottom edges
+    Torus,
+    /// The left and right edges are joined upside down, so that row `row` meets row `height - 1 - row`
+    Mobius,
+}
+
+/// How a `WrapGrid` is laid out for export
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// A rectangle, with the walls along each seam drawn on both edges it joins
+    Flat,
+    /// A cylinder bent into a ring around the centre, with the first row innermost.
+    /// Other wraps are laid out flat.
+    Ring,
+}
+
+/// An orthogonal `width` x `height` maze whose edges wrap around, so that the cells on either side
+/// of a seam are neighbours. Walls are owned as in `Grid`: the right wall of the last column is the
+/// seam between the left and right edges, and on a torus the seam between the top and bottom edges is
+/// the `inner_right_wall` of the first row. Both `width` and `height` should be at least 3.
+pub struct WrapGrid {
+    pub cell_size: u32,
+    pub width: usize,
+    pub height: usize,
+    pub wrap: Wrap,
+    pub layout: Layout,
+    pub solution: Vec<(usize, usize)>,
+    pub config: MazeConfig,
+}
+impl WrapGrid {
+
+    fn ring(&self) -> bool {
+        self.layout == Layout::Ring && self.wrap == Wrap::Cylinder
+    }
+
+    /// The top left corner of a cell laid out flat
+    fn corner(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        let margin = wall_width as f64;
+        let size = self.cell_size as f64;
+        (margin + column as f64 * size, margin + row as f64 * size)
+    }
+
+    /// The radius of the inner edge of the ring, where cells are as wide as they are deep
+    fn inner_radius(&self) -> f64 {
+        self.width as f64 * self.cell_size as f64 / TAU
+    }
+
+    /// The cell across the seam from the right edge of the last column in `row`
+    fn across_right_seam(&self, row: usize) -> (usize, usize) {
+        match self.wrap {
+            Wrap::Mobius => (self.height - 1 - row, 0),
+            Wrap::Cylinder | Wrap::Torus => (row, 0),
+        }
+    }
+
+    /// The cell across the seam from the left edge of the first column in `row`
+    fn across_left_seam(&self, row: usize) -> (usize, usize) {
+        match self.wrap {
+            Wrap::Mobius => (self.height - 1 - row, self.width - 1),
+            Wrap::Cylinder | Wrap::Torus => (row, self.width - 1),
+        }
+    }
+
+    fn ring_walls(&self, cells: &[Vec<Cell>], wall_width: u32) -> Walls {
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+        let centre = self.centre(wall_width);
+        let arc_angle = TAU / self.width as f64;
+
+        let mut arcs = Vec::new();
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            let inner_wall_radius = self.inner_radius() + size * row as f64;
+            let outer_wall_radius = inner_wall_radius + size;
+
+            for (column, cell) in row_cells.iter().enumerate() {
+
+                let angle_beginning = arc_angle * column as f64;
+                let angle_ending = angle_beginning + arc_angle;
+
+                if cell.inner_wall {
+                    arcs.push((inner_wall_radius, angle_beginning, angle_ending));
+                }
+                if cell.right_wall {
+                    let pole = (angle_ending.cos(), angle_ending.sin());
+                    lines.push((centre.add(pole.mul(inner_wall_radius - mid_wall)), centre.add(pole.mul(outer_wall_radius + mid_wall))));
+                }
+                if row == self.height - 1 && cell.outer_wall {
+                    arcs.push((outer_wall_radius, angle_beginning, angle_ending));
+                }
+            }
+        }
+
+        (arcs, lines)
+    }
+}
+
+impl Topology for WrapGrid {
+
+    /// Carves a maze entered through the bottom left cell. A torus has no edge to open,
+    /// so its solution runs from the entrance cell to the exit cell.
+    fn get_cells(&mut self, seed: [u32; 4]) -> Vec<Vec<Cell>> {
+        let (cells, solution) = self.carve(seed);
+        self.solution = solution;
+        cells
+    }
+
+    fn solution(&self) -> &[(usize, usize)] {
+        &self.solution
+    }
+
+    fn config(&self) -> &MazeConfig {
+        &self.config
+    }
+
+    fn rows(&self) -> usize {
+        self.height
+    }
+
+    fn columns(&self, _row: usize) -> usize {
+        self.width
+    }
+
+    fn neighbours(&self, row: usize, column: usize) -> Vec<(usize, usize)> {
+        let mut neighbours = Vec::with_capacity(4);
+
+        neighbours.push(if column + 1 < self.width { (row, column + 1) } else { self.across_right_seam(row) });
+        neighbours.push(if column > 0 { (row, column - 1) } else { self.across_left_seam(row) });
+
+        if row > 0 {
+            neighbours.push((row - 1, column));
+        } else if self.wrap == Wrap::Torus {
+            neighbours.push((self.height - 1, column));
+        }
+        if row + 1 < self.height {
+            neighbours.push((row + 1, column));
+        } else if self.wrap == Wrap::Torus {
+            neighbours.push((0, column));
+        }
+
+        neighbours
+    }
+
+    fn shared_wall(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> ((usize, usize), Wall) {
+        if column_a.abs_diff(column_b) == self.width - 1 {
+            // Across the left and right seam: the cell in the last column owns its right wall
+            if column_a > column_b { ((row_a, column_a), Wall::Right) } else { ((row_b, column_b), Wall::Right) }
+        } else if row_a == row_b {
+            // Same row: the leftmost cell owns its right wall
+            ((row_a, column_a.min(column_b)), Wall::Right)
+        } else if row_a.abs_diff(row_b) == self.height - 1 {
+            // Across the top and bottom seam of a torus: the cell in the first row owns it
+            ((0, column_a), Wall::InnerRight)
+        } else {
+            // Different rows: the lower cell owns its top wall
+            ((row_a.max(row_b), column_a), Wall::Inner)
+        }
+    }
+
+    /// Every step is one cell across, however the grid is laid out
+    fn edge_length(&self, _a: (usize, usize), _b: (usize, usize)) -> f64 {
+        self.cell_size as f64
+    }
+
+    /// The number of steps between two cells without walls, going either way around each seam
+    fn distance_estimate(&self, (row_a, column_a): (usize, usize), (row_b, column_b): (usize, usize)) -> f64 {
+        let across = column_a.abs_diff(column_b);
+        let columns = across.min(self.width - across);
+        let rows = match self.wrap {
+            Wrap::Cylinder => row_a.abs_diff(row_b),
+            Wrap::Torus => {
+                let down = row_a.abs_diff(row_b);
+                down.min(self.height - down)
+            }
+            // Crossing the seam turns the rows upside down
+            Wrap::Mobius => row_a.abs_diff(row_b).min(row_a.abs_diff(self.height - 1 - row_b)),
+        };
+        ((columns + rows) * self.cell_size as usize) as f64
+    }
+
+    fn boundary(&self) -> Vec<(usize, usize)> {
+        match self.wrap {
+            Wrap::Torus => Vec::new(),
+            Wrap::Cylinder | Wrap::Mobius => (0..self.width).map(|column| (self.height - 1, column)).collect(),
+        }
+    }
+
+    fn arcs_and_walls(
+        &self,
+        cells: &[Vec<Cell>],
+        wall_width: u32,
+    ) -> Walls {
+        if self.ring() {
+            return self.ring_walls(cells, wall_width);
+        }
+
+        let mid_wall = (wall_width / 2) as f64;
+        let size = self.cell_size as f64;
+        let torus = self.wrap == Wrap::Torus;
+
+        let mut lines = Vec::new();
+
+        for (row, row_cells) in cells.iter().enumerate() {
+            for (column, cell) in row_cells.iter().enumerate() {
+                let (left, top) = self.corner(row, column, wall_width);
+                let (right, bottom) = (left + size, top + size);
+
+                // The top edge of the first row is the seam of a torus, drawn again along the bottom edge
+                let top_wall = if row == 0 && torus { cell.inner_right_wall } else { cell.inner_wall };
+                if top_wall {
+                    lines.push(((left - mid_wall, top), (right + mid_wall, top)));
+                }
+                if cell.right_wall {
+                    lines.push(((right, top - mid_wall), (right, bottom + mid_wall)));
+                }
+                if row == self.height - 1 {
+                    let bottom_wall = if torus { cells[0][column].inner_right_wall } else { cell.outer_wall };
+                    if bottom_wall {
+                        lines.push(((left - mid_wall, bottom), (right + mid_wall, bottom)));
+                    }
+                }
+                // The right seam, drawn again along the left edge
+                if column == 0 {
+                    let (seam_row, seam_column) = self.across_left_seam(row);
+                    if cells[seam_row][seam_column].right_wall {
+                        lines.push(((left, top - mid_wall), (left, bottom + mid_wall)));
+                    }
+                }
+            }
+        }
+
+        (Vec::new(), lines)
+    }
+
+    fn cell_centre(&self, row: usize, column: usize, wall_width: u32) -> (f64, f64) {
+        if self.ring() {
+            let angle = TAU * (column as f64 + 0.5) / self.width as f64;
+            let radius = self.inner_radius() + self.cell_size as f64 * (row as f64 + 0.5);
+            return self.centre(wall_width).add((angle.cos(), angle.sin()).mul(radius));
+        }
+        let half = self.cell_size as f64 / 2.0;
+        let (left, top) = self.corner(row, column, wall_width);
+        (left + half, top + half)
+    }
+
+    fn dimensions(&self, wall_width: u32) -> (u32, u32) {
+        if self.ring() {
+            let radius = self.inner_radius() + (self.cell_size * self.height as u32 + wall_width) as f64;
+            let side = (2.0 * radius).ceil() as u32;
+            return (side, side);
+        }
+        let margin = 2 * wall_width;
+        (
+            self.cell_size * self.width as u32 + margin,
+            self.cell_size * self.height as u32 + margin,
+        )
+    }
+
+    /// Draws a path like `Topology::draw_path`, lifting the pen where it crosses a seam laid out flat
+    fn draw_path(&self, bitmap: &mut Bitmap, path: &[(usize, usize)], wall_width: u32) {
+        for step in path.windows(2) {
+            let ((row_a, column_a), (row_b, column_b)) = (step[0], step[1]);
+            if self.ring() || (row_a.abs_diff(row_b) <= 1 && column_a.abs_diff(column_b) <= 1) {
+                bitmap.line(self.cell_centre(row_a, column_a, wall_width), self.cell_centre(row_b, column_b, wall_width), RED, 1);
+            }
+        }
+    }
+
+    /// The annulus covered by a ring
+    fn footprint(&self, wall_width: u32) -> Bitmap {
+        let (width, height) = self.dimensions(wall_width);
+        if !self.ring() {
+            return Bitmap::from_image(image::RgbImage::from_pixel(width, height, BLACK));
+        }
+        let mut bitmap = Bitmap::with_dimensions(width, height);
+        let mid_wall = (wall_width / 2) as f64;
+        let outer_radius = self.inner_radius() + (self.cell_size * self.height as u32) as f64;
+        bitmap.annular_sector(self.centre(wall_width), self.inner_radius() - mid_wall, outer_radius + mid_wall, 0.0, TAU, BLACK);
+        bitmap
+    }
+
+    fn fill_cell(&self, bitmap: &mut Bitmap, row: usize, column: usize, wall_width: u32, rgb: Rgb<u8>) {
+        if !self.ring() {
+            let (left, top) = self.corner(row, column, wall_width);
+            let size = self.cell_size as f64;
+            bitmap.filled_rectangle(&[(left.round() as i32, top.round() as i32), ((left + size).round() as i32, (top + size).round() as i32)], rgb);
+            return;
+        }
+        let angle = TAU / self.width as f64;
+        let inner_radius = self.inner_radius() + self.cell_size as f64 * row as f64;
+        bitmap.annular_sector(self.centre(wall_width), inner_radius, inner_radius + self.cell_size as f64, angle * column as f64, angle * (column + 1) as f64, rgb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::maze::{algorithms::Algorithm, config::{Goal, MazeConfig}, topology::Topology};
+
+    use super::{Layout, Wrap, WrapGrid};
+
+    #[test]
+    fn seams_join_a_perfect_maze() {
+        use strum::IntoEnumIterator;
+
+        for wrap in [Wrap::Cylinder, Wrap::Torus, Wrap::Mobius] {
+            for algorithm in Algorithm::iter() {
+                // Without an exit, a torus has no opening and every wall between cells is shared
+                let config = MazeConfig { algorithm, goal: Goal::Farthest, ..Default::default() };
+                let mut grid = WrapGrid { cell_size: 10, width: 9, height: 6, wrap, layout: Layout::Flat, solution: Vec::new(), config };
+                let cells = grid.get_cells([11, 13, 15, 2]);
+
+                let mut passages = 0;
+                let mut seams = 0;
+                for row in 0..grid.rows() {
+                    for column in 0..grid.columns(row) {
+                        let neighbours = grid.neighbours(row, column);
+                        assert_eq!(neighbours.len(), if wrap == Wrap::Torus { 4 } else { 4 - (row == 0 || row == 5) as usize }, "{wrap:?}");
+                        for neighbour in neighbours {
+                            assert!(grid.neighbours(neighbour.0, neighbour.1).contains(&(row, column)), "{wrap:?} ({row}, {column}) {neighbour:?}");
+                            if grid.linked(&cells, (row, column), neighbour) {
+                                passages += 1;
+                                seams += (neighbour.1.abs_diff(column) > 1 || neighbour.0.abs_diff(row) > 1) as usize;
+                            }
+                        }
+                    }
+                }
+
+                // A spanning tree, counted from both ends of each passage
+                assert_eq!(passages, 2 * (9 * 6 - 1), "{wrap:?} {algorithm}");
+                assert!(seams > 0, "{wrap:?} {algorithm} never crosses a seam");
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs::File;
 
 use brickadia::{save::{Brick, BrickColor, Color, Direction, Rotation, Size}, write::SaveWriter};
-use brickadia::util::octree::Point;
+use brickadia::util::{get_scale_axis, octree::Point};
 
 use crate::metadata::headers;
 
@@ -9,6 +9,16 @@ use super::math::bounds;
 
 pub const BLACK_BRICK: Color = Color { r: 0, b: 0, g: 0, a: 0 };
 
+/// The default colour of the solution hint
+pub const HINT_BRICK: Color = Color { r: 255, b: 0, g: 0, a: 255 };
+
+/// The default colour of collectibles, while doors and keys take colours from a gradient
+pub const COLLECTIBLE_BRICK: Color = Color { r: 255, b: 0, g: 200, a: 255 };
+
+/// The default colours of the plates under and over a maze
+pub const FLOOR_BRICK: Color = Color { r: 128, b: 128, g: 128, a: 255 };
+pub const CEILING_BRICK: Color = Color { r: 40, b: 40, g: 40, a: 255 };
+
 pub const MAX_SIZE: i32 = 500;
 
 /// Finds the orientation of a wedge based on the position of the right-angle vertex
@@ -30,7 +40,8 @@ pub fn orientation(
     }
 }
 
-pub fn bricks_from_shapes(bricks: &mut Vec<Brick>, shapes: Vec<Vec<(i32, i32)>>, height: u32, pivot: Option<usize>, asset_name_index: u32) {
+/// `elevation` raises the bottom of every brick above the ground, in position units
+pub fn bricks_from_shapes(bricks: &mut Vec<Brick>, shapes: Vec<Vec<(i32, i32)>>, height: u32, elevation: i32, pivot: Option<usize>, asset_name_index: u32) {
 
     for vertices in shapes {
 
@@ -46,7 +57,7 @@ pub fn bricks_from_shapes(bricks: &mut Vec<Brick>, shapes: Vec<Vec<(i32, i32)>>,
             color: BrickColor::Unique(BLACK_BRICK),
             size: Size::Procedural(width as u32, length as u32, height as u32),
             asset_name_index,
-            position: (pos_x, pos_y, height as i32),
+            position: (pos_x, pos_y, elevation + height as i32),
             ..Default::default()
         };
 
@@ -61,6 +72,55 @@ pub fn bricks_from_shapes(bricks: &mut Vec<Brick>, shapes: Vec<Vec<(i32, i32)>>,
 }
 
 
+/// Adds boxes spanning `min..max` pixels along every world axis, split into bricks of at most `MAX_SIZE`.
+/// Each brick points along `direction`, which turns its own axes away from the world axes,
+/// so its size is given along the axes `get_scale_axis` maps them to.
+pub fn oriented_bricks(bricks: &mut Vec<Brick>, min: [i32; 3], max: [i32; 3], direction: Direction, asset_name_index: u32) {
+
+    let extents: [i32; 3] = std::array::from_fn(|axis| max[axis] - min[axis]);
+    if extents.iter().any(|&extent| extent <= 0) {
+        return;
+    }
+    let pieces: [i32; 3] = std::array::from_fn(|axis| (extents[axis] + MAX_SIZE - 1) / MAX_SIZE);
+
+    for i in 0..pieces[0] {
+        for j in 0..pieces[1] {
+            for k in 0..pieces[2] {
+                let piece = [i, j, k];
+                let bound = |axis: usize, index: i32| min[axis] + extents[axis] * index / pieces[axis];
+                let (low, high): ([i32; 3], [i32; 3]) = (
+                    std::array::from_fn(|axis| bound(axis, piece[axis])),
+                    std::array::from_fn(|axis| bound(axis, piece[axis] + 1)),
+                );
+
+                let mut brick = Brick {
+                    color: BrickColor::Unique(BLACK_BRICK),
+                    asset_name_index,
+                    direction,
+                    // Positions are in half pixels, at the centre of the box
+                    position: (low[0] + high[0], low[1] + high[1], low[2] + high[2]),
+                    ..Default::default()
+                };
+
+                let mut size = [0; 3];
+                for axis in 0..3 {
+                    size[get_scale_axis(&brick, axis as u8) as usize] = (high[axis] - low[axis]) as u32;
+                }
+                brick.size = Size::Procedural(size[0], size[1], size[2]);
+
+                bricks.push(brick);
+            }
+        }
+    }
+}
+
+/// Gives every brick the same colour, for layers which stand out from the black walls
+pub fn paint(bricks: &mut [Brick], colour: &Color) {
+    for brick in bricks {
+        brick.color = BrickColor::Unique(colour.clone());
+    }
+}
+
 pub fn save_bricks(bricks: Vec<Brick>, name: &str) {
 
     let (mut savedata, path) = headers::savedata(name.to_string());
@@ -1,10 +1,11 @@
 use std::any::type_name;
+use std::f64::consts::{FRAC_PI_2, TAU};
 
 use image::{ImageBuffer, Rgb, RgbImage};
 use image::imageops::FilterType; use imageproc::drawing::draw_hollow_rect_mut;
 use imageproc::rect::Rect;
 
-use imageproc::{drawing::{draw_filled_circle_mut, draw_line_segment_mut, draw_polygon_mut}, point::Point};
+use imageproc::{drawing::{draw_filled_circle_mut, draw_filled_rect_mut, draw_line_segment_mut, draw_polygon_mut}, point::Point};
 use num_traits::ToPrimitive;
 
 use super::math::bounds;
@@ -16,6 +17,27 @@ pub const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
 pub const RED: Rgb<u8> = Rgb([255, 0, 0]);
 pub const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
 
+/// How the ends of a thick arc are finished
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cap {
+    /// Squared off half the stroke width past the end
+    Square,
+    /// Rounded off with a half disc
+    Round,
+}
+
+/// The colours of `gradient`, from near to far
+const GRADIENT: [Rgb<u8>; 5] = [Rgb([48, 18, 160]), Rgb([24, 150, 220]), Rgb([40, 190, 90]), Rgb([250, 210, 40]), Rgb([210, 30, 30])];
+
+/// Blends the colours of a heat map palette, `t` going from 0 to 1
+pub fn gradient(t: f64) -> Rgb<u8> {
+    let position = t.clamp(0.0, 1.0) * (GRADIENT.len() - 1) as f64;
+    let index = (position.floor() as usize).min(GRADIENT.len() - 2);
+    let fraction = position - index as f64;
+    let (Rgb(from), Rgb(to)) = (GRADIENT[index], GRADIENT[index + 1]);
+    Rgb(std::array::from_fn(|channel| (from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * fraction).round() as u8))
+}
+
 
 pub fn in_bounds(image: &RgbImage, (x, y): (i32, i32)) -> bool {
     x >= 0 && y >= 0 && x < image.width() as i32 && y < image.height() as i32
@@ -32,6 +54,11 @@ impl Bitmap {
             image: ImageBuffer::from_pixel(image_width as u32, image_width as u32, WHITE),
         }
     }
+    pub fn with_dimensions(width: u32, height: u32) -> Self {
+        Self {
+            image: ImageBuffer::from_pixel(width, height, WHITE),
+        }
+    }
     pub fn from_image(image: RgbImage) -> Self {
         Self {
             image,
@@ -39,7 +66,9 @@ impl Bitmap {
     }
     
 
-    /// Draws an arc using cubic Bézier approximation.
+    /// Draws an arc `stroke_width` pixels thick, filling every pixel within half the stroke of `arc_radius`
+    /// between the two angles, and finishing both ends with `cap` unless the arc is a full circle.
+    #[allow(clippy::too_many_arguments)]
     pub fn arc(
         &mut self,
         centre: (f64, f64),
@@ -47,23 +76,109 @@ impl Bitmap {
         angle_begin: f64,
         angle_end: f64,
         rgb: Rgb<u8>,
-        stroke_width: u32
+        stroke_width: u32,
+        cap: Cap,
     ) {
-        // Determine the number of sample points based on the arc length.
-        let arc_length = (angle_end - angle_begin).abs() * arc_radius;
-        let num_samples = arc_length.ceil() as i32;
+        let (angle_begin, angle_end) = (angle_begin.min(angle_end), angle_begin.max(angle_end));
+        let half = stroke_width as f64 / 2.0;
+
+        self.annular_sector(centre, (arc_radius - half).max(0.0), arc_radius + half, angle_begin, angle_end, rgb);
+
+        if angle_end - angle_begin >= TAU {
+            return;
+        }
+
         let (cx, cy) = centre;
+        // Each end, with the direction pointing away from the rest of the arc
+        for (angle, outward) in [(angle_begin, -1.0), (angle_end, 1.0)] {
+            let (cos, sin) = (angle.cos(), angle.sin());
+            let end = (cx + arc_radius * cos, cy + arc_radius * sin);
+            let tangent = (-sin * outward, cos * outward);
+            let bounds = ((end.0 - half - 1.0, end.1 - half - 1.0), (end.0 + half + 1.0, end.1 + half + 1.0));
 
-        let circle_width = stroke_width as i32 / 2;
-        
-        for i in 0..=num_samples {
-            let theta = angle_begin + (angle_end - angle_begin) * (i as f64) / (num_samples as f64);
-            let x = (cx + arc_radius * theta.cos()).round() as i32;
-            let y = (cy + arc_radius * theta.sin()).round() as i32;
-            draw_filled_circle_mut(&mut self.image, (x, y), circle_width, rgb);
+            match cap {
+                Cap::Square => self.fill_where(bounds, rgb, |x, y| {
+                    let (dx, dy) = (x - end.0, y - end.1);
+                    let along = dx * tangent.0 + dy * tangent.1;
+                    let across = dx * cos + dy * sin;
+                    (0.0..half).contains(&along) && (-half..half).contains(&across)
+                }),
+                Cap::Round => self.fill_where(bounds, rgb, |x, y| {
+                    let (dx, dy) = (x - end.0, y - end.1);
+                    dx * dx + dy * dy < half * half
+                }),
+            }
         }
     }
 
+    pub fn disc(&mut self, (cx, cy): (f64, f64), radius: f64, rgb: Rgb<u8>) {
+        let centre = (cx.round() as i32, cy.round() as i32);
+        draw_filled_circle_mut(&mut self.image, centre, radius.round() as i32, rgb);
+    }
+
+    /// Fills the pixels between two radii and two angles, measured clockwise like `arc`.
+    /// Sectors sharing a radius or an angle neither overlap nor leave a gap between them.
+    pub fn annular_sector(
+        &mut self,
+        (cx, cy): (f64, f64),
+        inner_radius: f64,
+        outer_radius: f64,
+        angle_begin: f64,
+        angle_end: f64,
+        rgb: Rgb<u8>,
+    ) {
+        let sweep = angle_end - angle_begin;
+
+        // Only the box around the sector is searched, bounded by its corners and the outer radius where it crosses an axis
+        let bounds = if sweep >= TAU {
+            ((cx - outer_radius, cy - outer_radius), (cx + outer_radius, cy + outer_radius))
+        } else {
+            let first_quarter = (angle_begin / FRAC_PI_2).ceil() as i32;
+            let last_quarter = (angle_end / FRAC_PI_2).floor() as i32;
+            let points: Vec<(f64, f64)> = [(inner_radius, angle_begin), (outer_radius, angle_begin), (inner_radius, angle_end), (outer_radius, angle_end)]
+                .into_iter()
+                .chain((first_quarter..=last_quarter).map(|quarter| (outer_radius, quarter as f64 * FRAC_PI_2)))
+                .map(|(radius, angle)| (cx + radius * angle.cos(), cy + radius * angle.sin()))
+                .collect();
+            let min = |coordinate: fn(&(f64, f64)) -> f64| points.iter().map(coordinate).fold(f64::INFINITY, f64::min);
+            let max = |coordinate: fn(&(f64, f64)) -> f64| points.iter().map(coordinate).fold(f64::NEG_INFINITY, f64::max);
+            ((min(|p| p.0), min(|p| p.1)), (max(|p| p.0), max(|p| p.1)))
+        };
+
+        self.fill_where(bounds, rgb, |x, y| {
+            let (dx, dy) = (x - cx, y - cy);
+            let distance = (dx * dx + dy * dy).sqrt();
+            distance >= inner_radius && distance < outer_radius && (dy.atan2(dx) - angle_begin).rem_euclid(TAU) < sweep
+        });
+    }
+
+    /// Colours the pixels between two corners for which `inside` holds
+    fn fill_where(&mut self, (min, max): ((f64, f64), (f64, f64)), rgb: Rgb<u8>, inside: impl Fn(f64, f64) -> bool) {
+        let (width, height) = self.image.dimensions();
+
+        let x_range = (min.0.floor().max(0.0) as u32)..((max.0.ceil().max(0.0) as u32 + 1).min(width));
+        let y_range = (min.1.floor().max(0.0) as u32)..((max.1.ceil().max(0.0) as u32 + 1).min(height));
+
+        for y in y_range {
+            for x in x_range.clone() {
+                if inside(x as f64, y as f64) {
+                    self.image.put_pixel(x, y, rgb);
+                }
+            }
+        }
+    }
+
+    /// Fills a polygon, edges included
+    pub fn polygon(&mut self, points: &[(f64, f64)], rgb: Rgb<u8>) {
+        let mut points: Vec<Point<i32>> = points.iter().map(|&(x, y)| Point::new(x.round() as i32, y.round() as i32)).collect();
+        points.dedup();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() >= 3 {
+            draw_polygon_mut(&mut self.image, &points, rgb);
+        }
+    }
 
     pub fn line<T: ToPrimitive>(
         &mut self,
@@ -132,6 +247,23 @@ impl Bitmap {
         draw_hollow_rect_mut(&mut self.image, rectangle, rgb);
     }
 
+    pub fn filled_rectangle(
+        &mut self,
+        points: &[(i32, i32)],
+        rgb: Rgb<u8>,
+    ) {
+        let (min, max) = bounds(points);
+
+        let length = max.x.saturating_sub(min.x) as u32;
+        let width = max.y.saturating_sub(min.y) as u32;
+
+        if length == 0 || width == 0 {
+            return;
+        }
+
+        draw_filled_rect_mut(&mut self.image, Rect::at(min.x, min.y).of_size(length, width), rgb);
+    }
+
     // Downscales the image by applying a kernel convolution to the image pixels.
     pub fn downscale(&mut self, factor: u32) {
 
@@ -177,4 +309,33 @@ impl TupleUtils for RgbImage {
     fn put(&mut self, (x, y): (i32, i32), color: Rgb<u8>) {
         self.put_pixel(x as u32, y as u32, color)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitmap, Cap, BLACK};
+
+    fn covered(bitmap: &Bitmap) -> usize {
+        bitmap.image.pixels().filter(|&&pixel| pixel == BLACK).count()
+    }
+
+    #[test]
+    fn arcs_are_as_thick_as_their_stroke() {
+        for stroke_width in [1, 4, 5] {
+            let mut bitmap = Bitmap::with_dimensions(80, 80);
+            bitmap.arc((40.0, 40.0), 20.0, -0.5, 0.5, BLACK, stroke_width, Cap::Square);
+            // Across the middle of the arc, even widths included
+            let across = (0..80).filter(|&x| bitmap.image.get_pixel(x, 40) == &BLACK).count();
+            assert_eq!(across, stroke_width as usize);
+        }
+
+        // Caps reach half the stroke past the ends, round ones cutting the corners of square ones
+        let (mut butt, mut square, mut round) = (Bitmap::with_dimensions(80, 80), Bitmap::with_dimensions(80, 80), Bitmap::with_dimensions(80, 80));
+        butt.annular_sector((40.0, 40.0), 17.0, 23.0, 0.0, 1.0, BLACK);
+        square.arc((40.0, 40.0), 20.0, 0.0, 1.0, BLACK, 6, Cap::Square);
+        round.arc((40.0, 40.0), 20.0, 0.0, 1.0, BLACK, 6, Cap::Round);
+        assert!(covered(&butt) < covered(&round) && covered(&round) < covered(&square));
+        assert_eq!(square.image.get_pixel(60, 38), &BLACK);
+        assert_eq!(square.image.get_pixel(60, 36), &super::WHITE);
+    }
+}
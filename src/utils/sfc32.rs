@@ -30,4 +30,11 @@ impl SFC32 {
         let rand_value = (self.rand_f64() * range as f64) as usize;
         min + rand_value
     }
+    /// Shuffles `items` in place with the Fisher-Yates algorithm
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.rand_between(0, i + 1);
+            items.swap(i, j);
+        }
+    }
 }
\ No newline at end of file
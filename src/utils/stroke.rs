@@ -0,0 +1,121 @@
+use super::brick::MAX_SIZE;
+
+type Shapes = Vec<Vec<(i32, i32)>>;
+
+/// Tiles a straight wall of `stroke_width` pixels from `begin` to `end` with axis-aligned shapes,
+/// the same `(rectangles, triangles)` recovered from a `Bitmap` by `compute_edges` and `rectangular_decomposition`.
+/// The wall is cut into steps along its longer axis, each a rectangle between two right triangles
+/// which follow the slope of its sides. Triangles list their right-angle vertex first.
+pub fn stroke_shapes(begin: (f64, f64), end: (f64, f64), stroke_width: u32) -> (Shapes, Shapes) {
+
+    let (dx, dy) = (end.0 - begin.0, end.1 - begin.1);
+
+    // Walk along x, transposing steep walls so that their slope is at most 1
+    let steep = dy.abs() > dx.abs();
+    let transpose = |(x, y): (f64, f64)| if steep { (y, x) } else { (x, y) };
+    let (mut p0, mut p1) = (transpose(begin), transpose(end));
+    if p1.0 < p0.0 {
+        std::mem::swap(&mut p0, &mut p1);
+    }
+
+    let length = p1.0 - p0.0;
+    let slope = if length > 0.0 { (p1.1 - p0.1) / length } else { 0.0 };
+
+    // The half thickness of the wall measured across x
+    let half = stroke_width as f64 / 2.0 * (1.0 + slope * slope).sqrt();
+    let thickness = (2.0 * half).round() as i32;
+    let rise = (slope * length).abs();
+
+    // Each step must rise by less than the thickness, so that its rectangle is not empty
+    let steps = ((rise / (thickness - 1).max(1) as f64).ceil() as usize)
+        .max((length / MAX_SIZE as f64).ceil() as usize)
+        .max(1);
+
+    let mut rectangles = Vec::new();
+    let mut triangles = Vec::new();
+
+    let point = |x: i32, y: i32| if steep { (y, x) } else { (x, y) };
+
+    for step in 0..steps {
+        let xa = p0.0 + length * step as f64 / steps as f64;
+        let xb = p0.0 + length * (step + 1) as f64 / steps as f64;
+        let (ya, yb) = (p0.1 + slope * (xa - p0.0), p0.1 + slope * (xb - p0.0));
+
+        let (xa, xb) = (xa.round() as i32, xb.round() as i32);
+        if xa == xb {
+            continue;
+        }
+        // Both sides are rounded together, so that the wall keeps the same thickness along its length
+        let (low_a, low_b) = ((ya - half).round() as i32, (yb - half).round() as i32);
+        let (high_a, high_b) = (low_a + thickness, low_b + thickness);
+
+        let (bottom, top) = (low_a.max(low_b), high_a.min(high_b));
+        if bottom < top {
+            rectangles.push(vec![point(xa, bottom), point(xb, top)]);
+        }
+
+        // Between the sloped low side and the bottom of the rectangle
+        if low_a < low_b {
+            triangles.push(vec![point(xa, low_b), point(xa, low_a), point(xb, low_b)]);
+        } else if low_a > low_b {
+            triangles.push(vec![point(xb, low_a), point(xb, low_b), point(xa, low_a)]);
+        }
+
+        // Between the top of the rectangle and the sloped high side
+        if high_a < high_b {
+            triangles.push(vec![point(xb, high_a), point(xb, high_b), point(xa, high_a)]);
+        } else if high_a > high_b {
+            triangles.push(vec![point(xa, high_b), point(xa, high_a), point(xb, high_b)]);
+        }
+    }
+
+    (rectangles, triangles)
+}
+
+/// Splits an arc into chords which stray from it by at most half a pixel
+pub fn arc_chords(centre: (f64, f64), radius: f64, angle_begin: f64, angle_end: f64) -> Vec<((f64, f64), (f64, f64))> {
+
+    let sweep = angle_end - angle_begin;
+    let max_angle = if radius > 0.25 { 2.0 * (1.0 - 0.5 / radius).acos() } else { sweep.abs() };
+    let chords = (sweep.abs() / max_angle).ceil().max(1.0) as usize;
+
+    let point = |theta: f64| (centre.0 + radius * theta.cos(), centre.1 + radius * theta.sin());
+
+    (0..chords)
+        .map(|chord| {
+            let theta_a = angle_begin + sweep * chord as f64 / chords as f64;
+            let theta_b = angle_begin + sweep * (chord + 1) as f64 / chords as f64;
+            (point(theta_a), point(theta_b))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stroke_shapes;
+
+    /// The number of whole pixels covered by rectangles and half of those covered by triangles
+    fn area(begin: (f64, f64), end: (f64, f64), stroke_width: u32) -> f64 {
+        let (rectangles, triangles) = stroke_shapes(begin, end, stroke_width);
+        let size = |shape: &Vec<(i32, i32)>| {
+            let xs = shape.iter().map(|p| p.0);
+            let ys = shape.iter().map(|p| p.1);
+            ((xs.clone().max().unwrap() - xs.min().unwrap()) * (ys.clone().max().unwrap() - ys.min().unwrap())) as f64
+        };
+        rectangles.iter().map(size).sum::<f64>() + triangles.iter().map(size).sum::<f64>() / 2.0
+    }
+
+    #[test]
+    fn strokes_cover_the_wall() {
+        // Axis-aligned walls are single rectangles
+        assert_eq!(stroke_shapes((10.0, 20.0), (10.0, 60.0), 4), (vec![vec![(8, 20), (12, 60)]], Vec::new()));
+
+        for (begin, end) in [((0.0, 0.0), (40.0, 30.0)), ((5.0, 50.0), (25.0, 0.0)), ((100.0, 10.0), (0.0, 30.0))] {
+            let (dx, dy): (f64, f64) = (end.0 - begin.0, end.1 - begin.1);
+            let length = dx.abs().max(dy.abs());
+            let exact = length * 5.0 * (1.0 + (dx.abs().min(dy.abs()) / length).powi(2)).sqrt();
+            let covered = area(begin, end, 5);
+            assert!((covered - exact).abs() < exact * 0.1, "{covered} pixels cover a wall of {exact}");
+        }
+    }
+}
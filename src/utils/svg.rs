@@ -0,0 +1,113 @@
+use std::{f64::consts::PI, fmt, fs};
+
+use image::Rgb;
+
+use super::pixels::WHITE;
+
+/// A vector drawing with the same primitives as `Bitmap`, written out as an SVG document
+#[derive(Clone)]
+pub struct Svg {
+    pub width: u32,
+    pub height: u32,
+    elements: Vec<String>,
+}
+
+fn colour(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+impl Svg {
+    pub fn with_dimensions(width: u32, height: u32) -> Self {
+        let background = format!(r#"<rect width="{}" height="{}" fill="{}"/>"#, width, height, colour(WHITE));
+        Self { width, height, elements: vec![background] }
+    }
+
+    /// Draws an arc clockwise from `angle_begin` to `angle_end` with round ends, like `Bitmap::arc`
+    pub fn arc(
+        &mut self,
+        (cx, cy): (f64, f64),
+        arc_radius: f64,
+        angle_begin: f64,
+        angle_end: f64,
+        rgb: Rgb<u8>,
+        stroke_width: u32
+    ) {
+        let point = |theta: f64| (cx + arc_radius * theta.cos(), cy + arc_radius * theta.sin());
+
+        // An SVG arc cannot end where it begins, so a full circle is drawn as two halves
+        let sweep = angle_end - angle_begin;
+        let halves = if sweep.abs() >= 2.0 * PI - 1e-9 { 2 } else { 1 };
+
+        let (x, y) = point(angle_begin);
+        let mut path = format!("M {:.3} {:.3}", x, y);
+        for half in 1..=halves {
+            let theta = angle_begin + sweep * half as f64 / halves as f64;
+            let (x, y) = point(theta);
+            let large_arc = (sweep.abs() / halves as f64 > PI) as u8;
+            let clockwise = (sweep > 0.0) as u8;
+            path += &format!(" A {:.3} {:.3} 0 {} {} {:.3} {:.3}", arc_radius, arc_radius, large_arc, clockwise, x, y);
+        }
+
+        self.elements.push(format!(
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+            path, colour(rgb), stroke_width,
+        ));
+    }
+
+    /// Draws a straight line with flat ends, like `Bitmap::line`
+    pub fn line(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), rgb: Rgb<u8>, stroke_width: u32) {
+        self.elements.push(format!(
+            r#"<line x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}" stroke="{}" stroke-width="{}"/>"#,
+            x0, y0, x1, y1, colour(rgb), stroke_width,
+        ));
+    }
+
+    pub fn polyline(&mut self, points: &[(f64, f64)], rgb: Rgb<u8>, stroke_width: u32) {
+        let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.3},{:.3}", x, y)).collect();
+        self.elements.push(format!(
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round"/>"#,
+            points.join(" "), colour(rgb), stroke_width,
+        ));
+    }
+
+    pub fn save(&self, name: &str) -> &Self {
+        println!("Saving {}.svg", name);
+        fs::write(name.to_string() + ".svg", self.to_string()).expect("Failed to save SVG");
+        self
+    }
+}
+
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.width, self.height,
+        )?;
+        for element in &self.elements {
+            writeln!(f, "  {}", element)?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::utils::pixels::BLACK;
+
+    use super::Svg;
+
+    #[test]
+    fn arcs_are_exact_paths() {
+        let mut svg = Svg::with_dimensions(100, 100);
+        svg.arc((50.0, 50.0), 20.0, 0.0, PI / 2.0, BLACK, 3);
+        svg.arc((50.0, 50.0), 30.0, 0.0, 2.0 * PI, BLACK, 3);
+        let text = svg.to_string();
+
+        assert!(text.contains(r#"d="M 70.000 50.000 A 20.000 20.000 0 0 1 50.000 70.000""#), "{text}");
+        // A full circle is split in two, since an arc cannot end where it begins
+        assert!(text.contains("A 30.000 30.000 0 0 1 20.000 50.000 A 30.000 30.000 0 0 1 80.000 50.000"), "{text}");
+    }
+}
@@ -1,15 +1,17 @@
 
+use std::collections::BTreeMap;
+
 use rayon::iter::ParallelIterator;
-use brickadia::save::Brick;
-use image::RgbImage;
+use brickadia::save::{Brick, Color};
+use image::{Rgb, RgbImage};
 use imageproc::{drawing::draw_filled_rect_mut, rect::Rect};
 
 
 use crate::metadata::assets::BrickAssets;
 
 use super::{
-    brick::{bricks_from_shapes, MAX_SIZE}, 
-    pixels::{in_bounds, Bitmap, TupleUtils, BLACK, BLUE, RED}, 
+    brick::{bricks_from_shapes, paint, MAX_SIZE}, 
+    pixels::{in_bounds, Bitmap, TupleUtils, BLACK, BLUE, RED, WHITE}, 
     math::TupleMath, rectangle::RectUtils
 };
 
@@ -248,33 +250,83 @@ pub fn rectangular_decomposition(bitmap_less_edges: &mut Bitmap) -> Vec<Vec<(i32
 
 }
 
-pub fn brick_pixels(mut image: &mut Bitmap, height: u32) -> Vec<Brick> {
+pub fn brick_pixels(image: &mut Bitmap, height: u32) -> Vec<Brick> {
+    brick_pixels_at(image, height, 0)
+}
+
+/// Converts the black pixels into bricks whose bottom sits at `elevation`
+pub fn brick_pixels_at(image: &mut Bitmap, height: u32, elevation: i32) -> Vec<Brick> {
 
-    let (mut bitmap_less_edges, triangles) = compute_edges(&mut image);
+    let (mut bitmap_less_edges, triangles) = compute_edges(image);
 
     let rectangles: Vec<Vec<(i32, i32)>> = rectangular_decomposition(&mut bitmap_less_edges);
 
     let mut bricks: Vec<Brick> = Vec::new();
 
     // Modify `bricks` in place to add microbricks and microwedges
-    bricks_from_shapes(&mut bricks, rectangles, height, None, BrickAssets::MicroBrick.index() as u32);
+    bricks_from_shapes(&mut bricks, rectangles, height, elevation, None, BrickAssets::MicroBrick.index() as u32);
 
     let pivot_index = 0; // The right angle vertex
-    bricks_from_shapes(&mut bricks, triangles, height, Some(pivot_index), BrickAssets::MicroWedge.index() as u32);
+    bricks_from_shapes(&mut bricks, triangles, height, elevation, Some(pivot_index), BrickAssets::MicroWedge.index() as u32);
 
     bricks
 
 }
 
+/// Converts every colour of `image` except `background` into bricks of that colour, whose bottom sits at `elevation`.
+/// Each colour is decomposed on its own, as the black pixels of a mask around it, so that rectangles
+/// only merge pixels of the same colour and wedges follow the edges of each colour region.
+pub fn colour_bricks_at(image: &Bitmap, height: u32, elevation: i32, background: Option<Rgb<u8>>) -> Vec<Brick> {
+
+    let (width, image_height) = image.image.dimensions();
+
+    // The bounds of every colour, as (left, top, right, bottom) pixels
+    let mut regions: BTreeMap<[u8; 3], (u32, u32, u32, u32)> = BTreeMap::new();
+    for (x, y, &pixel) in image.image.enumerate_pixels() {
+        if Some(pixel) == background {
+            continue;
+        }
+        let bounds = regions.entry(pixel.0).or_insert((x, y, x, y));
+        *bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+    }
+
+    let mut bricks: Vec<Brick> = Vec::new();
+
+    for (rgb, (left, top, right, bottom)) in regions {
+        // A margin of one pixel keeps the edges of the region away from the edges of the mask
+        let (left, top) = (left.saturating_sub(1), top.saturating_sub(1));
+        let (right, bottom) = ((right + 2).min(width), (bottom + 2).min(image_height));
+
+        let mut mask = Bitmap::from_image(RgbImage::from_fn(right - left, bottom - top, |x, y| {
+            if image.image.get_pixel(left + x, top + y).0 == rgb { BLACK } else { WHITE }
+        }));
+
+        let mut colour_bricks = brick_pixels_at(&mut mask, height, elevation);
+        for brick in &mut colour_bricks {
+            brick.position.0 += 2 * left as i32;
+            brick.position.1 += 2 * top as i32;
+        }
+
+        let [r, g, b] = rgb;
+        paint(&mut colour_bricks, &Color { r, g, b, a: 255 });
+        bricks.extend(colour_bricks);
+    }
+
+    bricks
+}
+
 
 #[cfg(test)]
 mod tests {
 
     use image::{DynamicImage, RgbImage};
 
-    use crate::utils::{brick::save_bricks, pixels::Bitmap};
+    use brickadia::save::{BrickColor, Color, Size};
+    use image::Rgb;
+
+    use crate::utils::{brick::save_bricks, pixels::{Bitmap, WHITE}};
 
-    use super::brick_pixels;
+    use super::{brick_pixels, colour_bricks_at};
 
     #[test]
     fn compute_image() {
@@ -287,8 +339,26 @@ mod tests {
         save_bricks(bricks, "maze");
 
     }
-}
-
 
+    #[test]
+    fn colours_are_kept_apart() {
+        let (red, blue) = (Rgb([200, 30, 30]), Rgb([30, 30, 200]));
+        // A bar half red and half blue, on a white background
+        let mut image = RgbImage::from_pixel(20, 10, WHITE);
+        for x in 2..14 {
+            for y in 3..7 {
+                image.put_pixel(x, y, if x < 8 { red } else { blue });
+            }
+        }
 
+        let mut bricks = colour_bricks_at(&Bitmap::from_image(image), 2, 0, Some(WHITE));
+        bricks.sort_by_key(|brick| brick.position.0);
 
+        let colour = |Rgb([r, g, b]): Rgb<u8>| BrickColor::Unique(Color { r, g, b, a: 255 });
+        let summary: Vec<_> = bricks.iter().map(|brick| (brick.color.clone(), brick.size.clone(), brick.position)).collect();
+        assert_eq!(summary, vec![
+            (colour(red), Size::Procedural(6, 4, 2), (2 * 2 + 6, 2 * 3 + 4, 2)),
+            (colour(blue), Size::Procedural(6, 4, 2), (2 * 8 + 6, 2 * 3 + 4, 2)),
+        ]);
+    }
+}